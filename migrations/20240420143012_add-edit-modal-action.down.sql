@@ -0,0 +1,5 @@
+DELETE FROM actions WHERE "type" = 'editmodal';
+ALTER TYPE action_type RENAME TO action_type_old;
+CREATE TYPE action_type AS ENUM ('createmodal');
+ALTER TABLE actions ALTER COLUMN "type" TYPE action_type USING "type"::text::action_type;
+DROP TYPE action_type_old;
\ No newline at end of file
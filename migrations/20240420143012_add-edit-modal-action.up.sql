@@ -0,0 +1 @@
+ALTER TYPE action_type ADD VALUE 'editmodal';
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP INDEX todos_due_at_idx;
+DROP TABLE reminders;
+DROP TYPE reminder_kind;
\ No newline at end of file
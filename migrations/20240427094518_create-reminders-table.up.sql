@@ -0,0 +1,11 @@
+CREATE TYPE reminder_kind AS ENUM ('upcoming', 'overdue');
+CREATE TABLE reminders (
+	id uuid DEFAULT gen_random_uuid() NOT NULL,
+	todo_id uuid NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+	kind reminder_kind NOT NULL,
+	due_at timestamptz NOT NULL,
+	sent_at timestamptz DEFAULT now() NOT NULL,
+	CONSTRAINT reminders_pk PRIMARY KEY (id),
+	CONSTRAINT reminders_todo_kind_due_key UNIQUE (todo_id, kind, due_at)
+);
+CREATE INDEX todos_due_at_idx ON public.todos USING btree (due_at);
\ No newline at end of file
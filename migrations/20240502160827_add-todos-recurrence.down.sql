@@ -0,0 +1,3 @@
+DROP INDEX todos_recurs_from_idx;
+ALTER TABLE todos DROP COLUMN recurs_from;
+ALTER TABLE todos DROP COLUMN recurrence;
\ No newline at end of file
@@ -0,0 +1,3 @@
+ALTER TABLE todos ADD COLUMN recurrence TEXT;
+ALTER TABLE todos ADD COLUMN recurs_from UUID REFERENCES todos (id) ON DELETE SET NULL;
+CREATE UNIQUE INDEX todos_recurs_from_idx ON public.todos USING btree (recurs_from);
\ No newline at end of file
@@ -0,0 +1,2 @@
+CREATE TYPE todo_priority AS ENUM ('none', 'low', 'medium', 'high', 'urgent');
+ALTER TABLE todos ADD COLUMN priority todo_priority NOT NULL DEFAULT 'none';
\ No newline at end of file
@@ -0,0 +1,12 @@
+CREATE TABLE tags (
+	id uuid DEFAULT gen_random_uuid() NOT NULL,
+	name varchar(64) NOT NULL,
+	CONSTRAINT tags_pk PRIMARY KEY (id),
+	CONSTRAINT tags_name_key UNIQUE (name)
+);
+CREATE TABLE todo_tags (
+	todo_id uuid NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+	tag_id uuid NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+	CONSTRAINT todo_tags_pk PRIMARY KEY (todo_id, tag_id)
+);
+CREATE INDEX todo_tags_tag_id_idx ON public.todo_tags USING btree (tag_id);
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE todos ADD COLUMN created_by VARCHAR(24);
+UPDATE todos SET created_by = slack_user;
\ No newline at end of file
@@ -0,0 +1,2 @@
+DROP INDEX todos_search_idx;
+ALTER TABLE todos DROP COLUMN search;
\ No newline at end of file
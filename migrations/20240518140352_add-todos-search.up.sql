@@ -0,0 +1,5 @@
+ALTER TABLE todos ADD COLUMN search tsvector GENERATED ALWAYS AS (
+	setweight(to_tsvector('english', coalesce(title, '')), 'A') ||
+	setweight(to_tsvector('english', coalesce(description, '')), 'B')
+) STORED;
+CREATE INDEX todos_search_idx ON public.todos USING gin (search);
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE todos DROP COLUMN source_permalink;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE todos ADD COLUMN source_permalink TEXT;
\ No newline at end of file
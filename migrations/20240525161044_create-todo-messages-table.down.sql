@@ -0,0 +1 @@
+DROP TABLE todo_messages;
\ No newline at end of file
@@ -0,0 +1,7 @@
+CREATE TABLE todo_messages (
+	channel varchar(24) NOT NULL,
+	ts varchar(32) NOT NULL,
+	todo_id uuid NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+	CONSTRAINT todo_messages_pk PRIMARY KEY (channel, ts, todo_id)
+);
+CREATE INDEX todo_messages_todo_id_idx ON public.todo_messages USING btree (todo_id);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE preferences;
\ No newline at end of file
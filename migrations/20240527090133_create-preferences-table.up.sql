@@ -0,0 +1,8 @@
+CREATE TABLE preferences (
+	slack_user varchar(24) NOT NULL,
+	timezone text DEFAULT 'UTC' NOT NULL,
+	digest_enabled bool DEFAULT true NOT NULL,
+	digest_time time DEFAULT '09:00' NOT NULL,
+	digest_sent_on date,
+	CONSTRAINT preferences_pk PRIMARY KEY (slack_user)
+);
\ No newline at end of file
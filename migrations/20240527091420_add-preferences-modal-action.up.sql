@@ -0,0 +1 @@
+ALTER TYPE action_type ADD VALUE 'preferencesmodal';
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE installations;
\ No newline at end of file
@@ -0,0 +1,12 @@
+CREATE TABLE installations (
+	team_id varchar(24) DEFAULT '' NOT NULL,
+	enterprise_id varchar(24) DEFAULT '' NOT NULL,
+	team_name text,
+	bot_token text NOT NULL,
+	bot_user_id varchar(24),
+	scope text,
+	installed_by varchar(24),
+	installed_at timestamptz DEFAULT now() NOT NULL,
+	CONSTRAINT installations_pk PRIMARY KEY (enterprise_id, team_id)
+);
+CREATE INDEX installations_team_id_idx ON public.installations USING btree (team_id);
\ No newline at end of file
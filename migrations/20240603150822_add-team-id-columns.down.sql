@@ -0,0 +1,10 @@
+ALTER TABLE preferences DROP CONSTRAINT preferences_pk;
+DELETE FROM preferences a USING preferences b WHERE a.slack_user = b.slack_user AND (a.team_id, a.enterprise_id) > (b.team_id, b.enterprise_id);
+ALTER TABLE preferences ADD CONSTRAINT preferences_pk PRIMARY KEY (slack_user);
+ALTER TABLE preferences DROP COLUMN enterprise_id;
+ALTER TABLE preferences DROP COLUMN team_id;
+ALTER TABLE actions DROP COLUMN enterprise_id;
+ALTER TABLE actions DROP COLUMN team_id;
+DROP INDEX todos_team_id_slack_user_idx;
+ALTER TABLE todos DROP COLUMN enterprise_id;
+ALTER TABLE todos DROP COLUMN team_id;
\ No newline at end of file
@@ -0,0 +1,9 @@
+ALTER TABLE todos ADD COLUMN team_id varchar(24) DEFAULT '' NOT NULL;
+ALTER TABLE todos ADD COLUMN enterprise_id varchar(24) DEFAULT '' NOT NULL;
+CREATE INDEX todos_team_id_slack_user_idx ON public.todos USING btree (team_id, slack_user);
+ALTER TABLE actions ADD COLUMN team_id varchar(24) DEFAULT '' NOT NULL;
+ALTER TABLE actions ADD COLUMN enterprise_id varchar(24) DEFAULT '' NOT NULL;
+ALTER TABLE preferences ADD COLUMN team_id varchar(24) DEFAULT '' NOT NULL;
+ALTER TABLE preferences ADD COLUMN enterprise_id varchar(24) DEFAULT '' NOT NULL;
+ALTER TABLE preferences DROP CONSTRAINT preferences_pk;
+ALTER TABLE preferences ADD CONSTRAINT preferences_pk PRIMARY KEY (team_id, enterprise_id, slack_user);
\ No newline at end of file
@@ -2,11 +2,16 @@ use serde::Deserialize;
 use sqlx::{postgres::PgQueryResult, PgPool};
 use uuid::Uuid;
 
+use crate::tenant::Tenant;
+
 #[derive(Debug, Clone, Default, Copy, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "action_type", rename_all = "lowercase")]
+#[allow(clippy::enum_variant_names)]
 pub enum ActionType {
     #[default]
     CreateModal = 0,
+    EditModal = 1,
+    PreferencesModal = 2,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, sqlx::FromRow)]
@@ -16,6 +21,8 @@ pub struct Action {
     pub slack_user: String,
     pub slack_channel: String,
     pub r#type: ActionType,
+    pub team_id: String,
+    pub enterprise_id: String,
 }
 
 impl Action {
@@ -24,12 +31,15 @@ impl Action {
         slack_id: String,
         slack_user: String,
         slack_channel: String,
+        tenant: &Tenant,
     ) -> Self {
         Self {
             r#type,
             slack_id,
             slack_user,
             slack_channel,
+            team_id: tenant.team_id.clone(),
+            enterprise_id: tenant.enterprise_id.clone(),
             ..Default::default()
         }
     }
@@ -40,23 +50,31 @@ impl Action {
     }
 
     pub async fn insert(&self, db: &PgPool) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query(r#"INSERT INTO actions (id, "type", slack_id, slack_user, slack_channel) VALUES ($1, $2, $3, $4, $5)"#)
-        .bind(self.id).bind(self.r#type).bind(self.slack_id.clone()).bind(self.slack_user.clone()).bind(self.slack_channel.clone())
+        sqlx::query(r#"INSERT INTO actions (id, "type", slack_id, slack_user, slack_channel, team_id, enterprise_id) VALUES ($1, $2, $3, $4, $5, $6, $7)"#)
+        .bind(self.id).bind(self.r#type).bind(self.slack_id.clone()).bind(self.slack_user.clone()).bind(self.slack_channel.clone()).bind(self.team_id.clone()).bind(self.enterprise_id.clone())
         .execute(db).await
     }
 
     pub async fn delete(&self, db: &PgPool) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query(r#"DELETE FROM actions WHERE id=$1"#)
+        sqlx::query(r#"DELETE FROM actions WHERE id=$1 AND team_id=$2 AND enterprise_id=$3"#)
             .bind(self.id)
+            .bind(&self.team_id)
+            .bind(&self.enterprise_id)
             .execute(db)
             .await
     }
 
-    pub async fn fetch_slack_id(slack_id: String, db: &PgPool) -> Result<Self, sqlx::Error> {
+    pub async fn fetch_slack_id(
+        slack_id: String,
+        tenant: &Tenant,
+        db: &PgPool,
+    ) -> Result<Self, sqlx::Error> {
         sqlx::query_as::<sqlx::Postgres, Action>(
-            r#"SELECT * FROM actions WHERE slack_id = $1 LIMIT 1"#,
+            r#"SELECT * FROM actions WHERE slack_id = $1 AND team_id = $2 AND enterprise_id = $3 LIMIT 1"#,
         )
         .bind(slack_id)
+        .bind(&tenant.team_id)
+        .bind(&tenant.enterprise_id)
         .fetch_one(db)
         .await
     }
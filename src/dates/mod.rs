@@ -0,0 +1,54 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::slack::SlackApp;
+
+/// Looks up the timezone of a slack user, falling back to UTC
+pub async fn user_timezone(slack: &SlackApp, user: &str) -> Tz {
+    match slack.user_info(user).await {
+        Ok(v) => v.data["user"]["tz"]
+            .as_str()
+            .and_then(|tz| tz.parse().ok())
+            .unwrap_or(Tz::UTC),
+        Err(err) => {
+            println!("Couldn't fetch the timezone of {user}, using UTC. {err}");
+            Tz::UTC
+        }
+    }
+}
+
+/// Resolves a wall clock date and time in `tz` to an UTC instant
+pub fn resolve(date: NaiveDate, time: NaiveTime, tz: Tz) -> DateTime<Utc> {
+    let local = date.and_time(time);
+
+    // DST gaps don't have a local time, so push them forward by an hour
+    tz.from_local_datetime(&local)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(local + chrono::Duration::hours(1)))
+                .earliest()
+        })
+        .map(|v| v.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&local))
+}
+
+/// Today in `tz`
+pub fn today(tz: Tz) -> NaiveDate {
+    Utc::now().with_timezone(&tz).date_naive()
+}
+
+/// End of the working day, used when only a date is given
+pub fn end_of_day() -> NaiveTime {
+    NaiveTime::from_hms_opt(23, 59, 0).unwrap() // Unwrap kept; Hardcoded data
+}
+
+/// Formats a date with slack's date syntax, so each reader sees it in their own timezone
+pub fn slack_date(date: DateTime<Utc>) -> String {
+    format!(
+        "<!date^{}^{{date_short_pretty}} at {{time}}|{}>",
+        date.timestamp(),
+        date.format("%Y-%m-%d %H:%M UTC")
+    )
+}
+
+pub mod natural;
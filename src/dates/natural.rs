@@ -0,0 +1,303 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+
+use crate::recurrence;
+
+use super::{end_of_day, resolve};
+
+/// Parses a due date in the user's timezone `tz`, relative to `now`. Understands days
+/// (`tomorrow`, `next tue`, `friday`, `2026-11-01`), times (`3pm`, `17:00`, `noon`) and
+/// combinations of both (`friday 3pm`), offsets (`in 3 days`, `in 2h`) and `eod`.
+/// A day without a time is due at the end of that day
+pub fn parse(s: &str, now: DateTime<Utc>, tz: Tz) -> Option<DateTime<Utc>> {
+    let s = s.to_lowercase();
+    let words = s
+        .split_whitespace()
+        .filter(|w| *w != "at" && *w != "on")
+        .collect::<Vec<&str>>();
+    let today = now.with_timezone(&tz).date_naive();
+
+    match words.as_slice() {
+        [] => None,
+        ["eod"] | ["end", "of", "day"] => Some(resolve(today, end_of_day(), tz)),
+        ["in", compact] => split_offset(compact)
+            .and_then(|(n, unit)| offset(n, unit))
+            .map(|offset| now + offset),
+        ["in", n, unit] => offset(n, unit).map(|offset| now + offset),
+        [.., last] if parse_time(last).is_some() => {
+            let time = parse_time(last)?;
+
+            match &words[..words.len() - 1] {
+                // A bare time is the next time the clock shows it
+                [] => {
+                    let due = resolve(today, time, tz);
+                    if due > now {
+                        Some(due)
+                    } else {
+                        Some(resolve(today + Duration::days(1), time, tz))
+                    }
+                }
+                day => Some(resolve(parse_day_words(day, today)?, time, tz)),
+            }
+        }
+        [first, day @ ..] if parse_time(first).is_some() && !day.is_empty() => Some(resolve(
+            parse_day_words(day, today)?,
+            parse_time(first)?,
+            tz,
+        )),
+        day => Some(resolve(parse_day_words(day, today)?, end_of_day(), tz)),
+    }
+}
+
+/// Parses a single day relative to `today`: `today`, `tomorrow`, `yesterday`, a weekday or `2026-11-01`
+pub fn parse_day(s: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match s.to_lowercase().as_str() {
+        "today" | "tonight" => Some(today),
+        "tomorrow" | "tmrw" => Some(today + Duration::days(1)),
+        "yesterday" => Some(today - Duration::days(1)),
+        s => match recurrence::weekday(s) {
+            // The upcoming weekday, today included
+            Some(day) => (0..7)
+                .map(|offset| today + Duration::days(offset))
+                .find(|d| d.weekday() == day),
+            None => NaiveDate::parse_from_str(s, "%Y-%m-%d").ok(),
+        },
+    }
+}
+
+/// Parses a time of day: `17:00`, `5pm`, `5:30pm`, `noon` or `midnight`
+pub fn parse_time(s: &str) -> Option<NaiveTime> {
+    let s = s.to_lowercase();
+
+    match s.as_str() {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+
+    if let Ok(time) = NaiveTime::parse_from_str(&s, "%H:%M") {
+        return Some(time);
+    }
+
+    let (clock, pm) = match (s.strip_suffix("am"), s.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, false),
+        (_, Some(clock)) => (clock, true),
+        _ => return None,
+    };
+    let (hour, minute) = clock.split_once(':').unwrap_or((clock, "0"));
+    let (hour, minute) = (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?);
+
+    if !(1..=12).contains(&hour) {
+        return None;
+    }
+
+    NaiveTime::from_hms_opt(hour % 12 + if pm { 12 } else { 0 }, minute, 0)
+}
+
+/// A day made of one or two words, `friday` or `next friday`
+fn parse_day_words(words: &[&str], today: NaiveDate) -> Option<NaiveDate> {
+    match words {
+        [day] => parse_day(day, today),
+        ["next", "week"] => Some(today + Duration::weeks(1)),
+        // The weekday after today, never today itself
+        ["next", day] => {
+            let day = recurrence::weekday(day)?;
+            (1..=7)
+                .map(|offset| today + Duration::days(offset))
+                .find(|d| d.weekday() == day)
+        }
+        _ => None,
+    }
+}
+
+/// Splits a compact offset like `3d` into its amount and unit
+fn split_offset(s: &str) -> Option<(&str, &str)> {
+    let at = s.find(|c: char| !c.is_ascii_digit())?;
+    Some(s.split_at(at))
+}
+
+fn offset(n: &str, unit: &str) -> Option<Duration> {
+    let n = match n {
+        "a" | "an" | "one" => 1,
+        n => n.parse::<i64>().ok().filter(|n| *n > 0)?,
+    };
+
+    match unit {
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(Duration::minutes(n)),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(Duration::hours(n)),
+        "d" | "day" | "days" => Some(Duration::days(n)),
+        "w" | "wk" | "wks" | "week" | "weeks" => Some(Duration::weeks(n)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    /// Wednesday 2024-05-08, 10:00 UTC
+    fn now() -> DateTime<Utc> {
+        utc("2024-05-08T10:00:00Z")
+    }
+
+    #[test]
+    fn relative_days() {
+        for (text, due) in [
+            ("today", "2024-05-08T23:59:00Z"),
+            ("tonight", "2024-05-08T23:59:00Z"),
+            ("eod", "2024-05-08T23:59:00Z"),
+            ("end of day", "2024-05-08T23:59:00Z"),
+            ("tomorrow", "2024-05-09T23:59:00Z"),
+            ("Tmrw", "2024-05-09T23:59:00Z"),
+            ("next week", "2024-05-15T23:59:00Z"),
+            ("2026-11-01", "2026-11-01T23:59:00Z"),
+            ("in 3 days", "2024-05-11T10:00:00Z"),
+            ("in 2h", "2024-05-08T12:00:00Z"),
+            ("in an hour", "2024-05-08T11:00:00Z"),
+            ("in 1w", "2024-05-15T10:00:00Z"),
+        ] {
+            assert_eq!(parse(text, now(), Tz::UTC), Some(utc(due)), "{text}");
+        }
+    }
+
+    #[test]
+    fn weekdays_wrap_into_next_week() {
+        for (text, due) in [
+            ("wednesday", "2024-05-08T23:59:00Z"),
+            ("friday", "2024-05-10T23:59:00Z"),
+            ("mon", "2024-05-13T23:59:00Z"),
+            ("tuesday", "2024-05-14T23:59:00Z"),
+            ("next wed", "2024-05-15T23:59:00Z"),
+            ("next friday", "2024-05-10T23:59:00Z"),
+            ("on monday at 9am", "2024-05-13T09:00:00Z"),
+        ] {
+            assert_eq!(parse(text, now(), Tz::UTC), Some(utc(due)), "{text}");
+        }
+
+        assert_eq!(
+            parse_day("sunday", date("2024-05-08")),
+            Some(date("2024-05-12"))
+        );
+        assert_eq!(
+            parse_day("tue", date("2024-05-08")),
+            Some(date("2024-05-14"))
+        );
+    }
+
+    #[test]
+    fn times_of_day() {
+        for (text, due) in [
+            ("3pm", "2024-05-08T15:00:00Z"),
+            // Already past today, so tomorrow
+            ("9am", "2024-05-09T09:00:00Z"),
+            ("10:00", "2024-05-09T10:00:00Z"),
+            ("17:30", "2024-05-08T17:30:00Z"),
+            ("friday 5:30pm", "2024-05-10T17:30:00Z"),
+            ("noon tomorrow", "2024-05-09T12:00:00Z"),
+            ("tomorrow midnight", "2024-05-09T00:00:00Z"),
+        ] {
+            assert_eq!(parse(text, now(), Tz::UTC), Some(utc(due)), "{text}");
+        }
+    }
+
+    #[test]
+    fn twelve_and_twenty_four_hour_clocks() {
+        for (text, time) in [
+            ("12am", "00:00"),
+            ("12pm", "12:00"),
+            ("1am", "01:00"),
+            ("5:30pm", "17:30"),
+            ("11:59PM", "23:59"),
+            ("00:00", "00:00"),
+            ("17:00", "17:00"),
+            ("noon", "12:00"),
+            ("midnight", "00:00"),
+        ] {
+            assert_eq!(
+                parse_time(text),
+                Some(NaiveTime::parse_from_str(time, "%H:%M").unwrap()),
+                "{text}"
+            );
+        }
+
+        for text in ["0am", "13pm", "5:75pm", "25:00", "5", "pm", "five pm"] {
+            assert_eq!(parse_time(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn dst_boundaries() {
+        // Europe/Amsterdam skips 02:00-03:00 on 2024-03-31 and repeats it on 2024-10-27
+        let tz: Tz = "Europe/Amsterdam".parse().unwrap();
+        let before_spring = utc("2024-03-30T12:00:00Z");
+        let before_fall = utc("2024-10-26T12:00:00Z");
+
+        // Winter time before the switch, summer time after it
+        assert_eq!(
+            parse("today 5pm", before_spring, tz),
+            Some(utc("2024-03-30T16:00:00Z"))
+        );
+        assert_eq!(
+            parse("tomorrow 9am", before_spring, tz),
+            Some(utc("2024-03-31T07:00:00Z"))
+        );
+        // Inside the gap, pushed forward by an hour
+        assert_eq!(
+            parse("tomorrow 2:30am", before_spring, tz),
+            Some(utc("2024-03-31T01:30:00Z"))
+        );
+        // Ambiguous, the first of the two
+        assert_eq!(
+            parse("tomorrow 2:30am", before_fall, tz),
+            Some(utc("2024-10-27T00:30:00Z"))
+        );
+        assert_eq!(
+            parse("tomorrow", before_fall, tz),
+            Some(utc("2024-10-27T22:59:00Z"))
+        );
+        // Offsets are exact durations, whatever the clock does
+        assert_eq!(
+            parse("in 1 day", before_spring, tz),
+            Some(utc("2024-03-31T12:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn days_are_taken_in_the_user_timezone() {
+        // Already Thursday in Tokyo
+        let tz: Tz = "Asia/Tokyo".parse().unwrap();
+
+        assert_eq!(
+            parse("tomorrow 9am", utc("2024-05-08T20:00:00Z"), tz),
+            Some(utc("2024-05-10T00:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_doesnt_understand() {
+        for text in [
+            "",
+            "   ",
+            "someday",
+            "next",
+            "next month",
+            "in 0 days",
+            "in -1 days",
+            "in 3 fortnights",
+            "in",
+            "2024-02-30",
+            "friday 25:00",
+            "the day after tomorrow",
+        ] {
+            assert_eq!(parse(text, now(), Tz::UTC), None, "{text}");
+        }
+    }
+}
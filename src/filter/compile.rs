@@ -0,0 +1,369 @@
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::{dates, tenant::Tenant, todo::Priority};
+
+use super::{Comparison, Condition, DueValue, Filter, Keyset, Sort, Status};
+
+/// Sorts priorities from urgent to none when ascending
+const PRIORITY_KEY: &str = "-array_position(enum_range(NULL::todo_priority), t.priority)";
+
+/// Sorts todos without a due date last when ascending
+const DUE_KEY: &str = "COALESCE(t.due_at, 'infinity')";
+
+impl Filter {
+    /// Whose todos are listed, the requesting user unless someone was mentioned
+    pub fn owner<'a>(&'a self, caller: &'a str) -> &'a str {
+        self.assignee
+            .as_ref()
+            .map(|user| user.id.as_str())
+            .unwrap_or(caller)
+    }
+
+    /// Days can only be resolved once the requesting user's timezone is known
+    pub fn needs_timezone(&self) -> bool {
+        self.conditions
+            .iter()
+            .any(|c| matches!(c, Condition::Due(_, DueValue::Day(_))))
+    }
+
+    /// Pushes the `WHERE` clause for todos aliased as `t` of the `tenant` workspace, every value
+    /// is a bound parameter
+    pub fn push_where(
+        &self,
+        qb: &mut QueryBuilder<'_, Postgres>,
+        caller: &str,
+        tenant: &Tenant,
+        tz: Tz,
+    ) {
+        qb.push(" WHERE t.deleted_at IS NULL AND t.slack_user = ");
+        qb.push_bind(self.owner(caller).to_string());
+        qb.push(" AND t.team_id = ");
+        qb.push_bind(tenant.team_id.clone());
+        qb.push(" AND t.enterprise_id = ");
+        qb.push_bind(tenant.enterprise_id.clone());
+
+        for condition in &self.conditions {
+            qb.push(" AND ");
+
+            match condition {
+                Condition::Status(Status::Open) => {
+                    qb.push("t.completed IS NOT TRUE");
+                }
+                Condition::Status(Status::Done) => {
+                    qb.push("t.completed IS TRUE");
+                }
+                Condition::Tag(tag) => {
+                    qb.push("EXISTS (SELECT 1 FROM todo_tags tt JOIN tags g ON g.id = tt.tag_id WHERE tt.todo_id = t.id AND g.name = ");
+                    qb.push_bind(tag.clone());
+                    qb.push(")");
+                }
+                Condition::Priority(op, priority) => {
+                    qb.push("t.priority ");
+                    qb.push(operator(*op));
+                    qb.push(" ");
+                    qb.push_bind(*priority);
+                }
+                Condition::Due(_, DueValue::None) => {
+                    qb.push("t.due_at IS NULL");
+                }
+                Condition::Due(_, DueValue::Overdue) => {
+                    qb.push("t.completed IS NOT TRUE AND t.due_at < now()");
+                }
+                Condition::Due(op, DueValue::Day(day)) => {
+                    let date = dates::natural::parse_day(day, dates::today(tz)).unwrap_or_default();
+                    let start = dates::resolve(date, chrono::NaiveTime::MIN, tz);
+                    let end = dates::resolve(date + Duration::days(1), chrono::NaiveTime::MIN, tz);
+
+                    match op {
+                        Comparison::Eq => {
+                            qb.push("t.due_at >= ");
+                            qb.push_bind(start);
+                            qb.push(" AND t.due_at < ");
+                            qb.push_bind(end);
+                        }
+                        Comparison::Lt => {
+                            qb.push("t.due_at < ");
+                            qb.push_bind(start);
+                        }
+                        Comparison::Le => {
+                            qb.push("t.due_at < ");
+                            qb.push_bind(end);
+                        }
+                        Comparison::Gt => {
+                            qb.push("t.due_at >= ");
+                            qb.push_bind(end);
+                        }
+                        Comparison::Ge => {
+                            qb.push("t.due_at >= ");
+                            qb.push_bind(start);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Pushes the `ORDER BY` clause, `reverse` walks the listing backwards
+    pub fn push_order(&self, qb: &mut QueryBuilder<'_, Postgres>, reverse: bool) {
+        let direction = if reverse { " DESC" } else { " ASC" };
+
+        qb.push(" ORDER BY ");
+        qb.push(
+            self.sort_keys()
+                .iter()
+                .map(|key| format!("{key}{direction}"))
+                .collect::<Vec<String>>()
+                .join(", "),
+        );
+    }
+
+    /// Pushes a row comparison that holds for todos sorted after `keyset` with `Gt`, or before
+    /// it with `Lt`
+    pub fn push_keyset(
+        &self,
+        qb: &mut QueryBuilder<'_, Postgres>,
+        op: Comparison,
+        keyset: &Keyset,
+    ) {
+        qb.push("(");
+        qb.push(self.sort_keys().join(", "));
+        qb.push(") ");
+        qb.push(operator(op));
+        qb.push(" (");
+
+        match self.sort {
+            Sort::Priority => {
+                push_priority_key(qb, keyset.priority);
+                qb.push(", ");
+                push_due_key(qb, keyset.due_at);
+            }
+            Sort::Due => {
+                push_due_key(qb, keyset.due_at);
+                qb.push(", ");
+                push_priority_key(qb, keyset.priority);
+            }
+            Sort::Title => {
+                qb.push("lower(");
+                qb.push_bind(keyset.title.clone().unwrap_or_default());
+                qb.push(")");
+            }
+        }
+
+        qb.push(", ");
+        qb.push_bind(keyset.id);
+        qb.push(")");
+    }
+
+    /// Sort keys of the listing, all ascending so rows compare as a whole
+    fn sort_keys(&self) -> &'static [&'static str] {
+        match self.sort {
+            Sort::Priority => &[PRIORITY_KEY, DUE_KEY, "t.id"],
+            Sort::Due => &[DUE_KEY, PRIORITY_KEY, "t.id"],
+            Sort::Title => &["lower(t.title)", "t.id"],
+        }
+    }
+}
+
+/// The priority key of a keyset, matching `PRIORITY_KEY`
+fn push_priority_key(qb: &mut QueryBuilder<'_, Postgres>, priority: Priority) {
+    qb.push("-array_position(enum_range(NULL::todo_priority), ");
+    qb.push_bind(priority);
+    qb.push(")");
+}
+
+/// The due key of a keyset, matching `DUE_KEY`
+fn push_due_key(qb: &mut QueryBuilder<'_, Postgres>, due_at: Option<DateTime<Utc>>) {
+    qb.push("COALESCE(");
+    qb.push_bind(due_at);
+    qb.push("::timestamptz, 'infinity')");
+}
+
+fn operator(op: Comparison) -> &'static str {
+    match op {
+        Comparison::Eq => "=",
+        Comparison::Lt => "<",
+        Comparison::Le => "<=",
+        Comparison::Gt => ">",
+        Comparison::Ge => ">=",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use sqlx::PgPool;
+
+    use crate::{filter::parse::parse, todo::Todo};
+
+    use super::*;
+
+    async fn insert(title: &str, priority: Priority, due_in: Option<i64>, db: &PgPool) {
+        let mut todo = Todo {
+            title: title.to_string(),
+            slack_user: "U1".to_string(),
+            priority,
+            due_at: due_in.map(|v| Utc::now() + Duration::days(v)),
+            ..Default::default()
+        };
+        todo.assign_id().insert(db).await.unwrap();
+    }
+
+    /// Titles of every page, walking forward through the listing of `text`
+    async fn pages(text: &str, db: &PgPool) -> Vec<Vec<String>> {
+        let filter = parse(text).unwrap();
+        let mut after: Option<Keyset> = None;
+        let mut pages = Vec::new();
+
+        loop {
+            let mut qb = QueryBuilder::<Postgres>::new("SELECT t.* FROM todos t");
+            filter.push_where(&mut qb, "U1", &Tenant::default(), Tz::UTC);
+            if let Some(after) = &after {
+                qb.push(" AND ");
+                filter.push_keyset(&mut qb, Comparison::Gt, after);
+            }
+            filter.push_order(&mut qb, false);
+            qb.push(" LIMIT 2");
+
+            let todos = qb.build_query_as::<Todo>().fetch_all(db).await.unwrap();
+            match todos.last() {
+                Some(last) => after = Some(Keyset::of(last, filter.sort)),
+                None => return pages,
+            }
+            pages.push(todos.into_iter().map(|v| v.title).collect());
+        }
+    }
+
+    fn compile(text: &str) -> String {
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM todos t");
+        parse(text)
+            .unwrap()
+            .push_where(&mut qb, "U1", &Tenant::default(), Tz::UTC);
+
+        qb.sql().to_string()
+    }
+
+    fn binds(sql: &str) -> usize {
+        sql.matches('$').count()
+    }
+
+    /// Owner and workspace are always bound
+    const SCOPE: &str = "SELECT COUNT(*) FROM todos t WHERE t.deleted_at IS NULL AND t.slack_user = $1 AND t.team_id = $2 AND t.enterprise_id = $3";
+
+    #[test]
+    fn scopes_by_owner_and_workspace() {
+        let sql = compile("");
+
+        assert_eq!(sql, SCOPE);
+        assert_eq!(binds(&sql), 3);
+    }
+
+    #[test]
+    fn compiles_status_tag_and_priority() {
+        let sql = compile("status:open #backend priority:>=high");
+
+        assert_eq!(
+            sql,
+            format!(
+                "{SCOPE} AND t.completed IS NOT TRUE AND EXISTS (SELECT 1 FROM todo_tags tt JOIN tags g ON g.id = tt.tag_id WHERE tt.todo_id = t.id AND g.name = $4) AND t.priority >= $5"
+            )
+        );
+        assert_eq!(binds(&sql), 5);
+    }
+
+    #[test]
+    fn compiles_due_days_to_ranges() {
+        assert_eq!(
+            compile("due:today"),
+            format!("{SCOPE} AND t.due_at >= $4 AND t.due_at < $5")
+        );
+        assert_eq!(compile("due:<today"), format!("{SCOPE} AND t.due_at < $4"));
+        assert_eq!(compile("due:<=today"), format!("{SCOPE} AND t.due_at < $4"));
+        assert_eq!(compile("due:>today"), format!("{SCOPE} AND t.due_at >= $4"));
+        assert_eq!(
+            compile("due:>=today"),
+            format!("{SCOPE} AND t.due_at >= $4")
+        );
+        assert_eq!(
+            compile("due:none status:done"),
+            format!("{SCOPE} AND t.due_at IS NULL AND t.completed IS TRUE")
+        );
+        assert_eq!(
+            compile("due:overdue"),
+            format!("{SCOPE} AND t.completed IS NOT TRUE AND t.due_at < now()")
+        );
+    }
+
+    #[test]
+    fn lists_the_mentioned_user() {
+        assert_eq!(parse("<@U9|bob>").unwrap().owner("U1"), "U9");
+        assert_eq!(parse("").unwrap().owner("U1"), "U1");
+    }
+
+    #[sqlx::test]
+    async fn compiled_filters_run(db: PgPool) {
+        insert("a", Priority::High, Some(0), &db).await;
+
+        for text in [
+            "status:open #backend priority:>=high",
+            "due:<=friday sort:due",
+            "due:none status:done sort:title",
+            "due:overdue",
+        ] {
+            let mut qb = QueryBuilder::<Postgres>::new(crate::todo::TODO_SELECT);
+            let filter = parse(text).unwrap();
+            filter.push_where(&mut qb, "U1", &Tenant::default(), Tz::UTC);
+            filter.push_order(&mut qb, false);
+
+            assert!(qb.build().fetch_all(&db).await.is_ok(), "{text}");
+        }
+    }
+
+    #[sqlx::test]
+    async fn keyset_pages_follow_the_sort(db: PgPool) {
+        insert("b", Priority::Low, None, &db).await;
+        insert("C", Priority::Urgent, Some(3), &db).await;
+        insert("a", Priority::Urgent, Some(1), &db).await;
+        insert("d", Priority::None, Some(2), &db).await;
+        insert("e", Priority::Low, Some(5), &db).await;
+
+        assert_eq!(
+            pages("sort:priority", &db).await,
+            vec![vec!["a", "C"], vec!["e", "b"], vec!["d"]]
+        );
+        assert_eq!(
+            pages("sort:due", &db).await,
+            vec![vec!["a", "d"], vec!["C", "e"], vec!["b"]]
+        );
+        assert_eq!(
+            pages("sort:title", &db).await,
+            vec![vec!["a", "b"], vec!["C", "d"], vec!["e"]]
+        );
+    }
+
+    #[sqlx::test]
+    async fn keyset_walks_back_in_reverse(db: PgPool) {
+        insert("a", Priority::Urgent, None, &db).await;
+        insert("b", Priority::High, None, &db).await;
+        insert("c", Priority::Low, None, &db).await;
+
+        let filter = parse("sort:priority").unwrap();
+        let c: Todo = sqlx::query_as("SELECT * FROM todos WHERE title = 'c'")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT t.* FROM todos t");
+        filter.push_where(&mut qb, "U1", &Tenant::default(), Tz::UTC);
+        qb.push(" AND ");
+        filter.push_keyset(&mut qb, Comparison::Lt, &Keyset::of(&c, filter.sort));
+        filter.push_order(&mut qb, true);
+
+        let todos = qb.build_query_as::<Todo>().fetch_all(&db).await.unwrap();
+        assert_eq!(
+            todos.into_iter().map(|v| v.title).collect::<Vec<_>>(),
+            vec!["b", "a"]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    slack::escape::SlackEscape,
+    todo::{Priority, Todo},
+};
+
+/// Longest title kept in a keyset, button values are limited to 2000 characters
+const KEYSET_TITLE: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Open,
+    Done,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DueValue {
+    None,
+    Overdue,
+    /// A day as typed, resolved in the requesting user's timezone when compiled
+    Day(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Status(Status),
+    Tag(String),
+    Due(Comparison, DueValue),
+    Priority(Comparison, Priority),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Sort {
+    #[default]
+    Priority,
+    Due,
+    Title,
+}
+
+/// Where a page of a listing starts or ends, the sort keys of a todo and its id
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyset {
+    pub priority: Priority,
+    pub due_at: Option<DateTime<Utc>>,
+    /// Only kept when sorting by title. A long title is cut short, which can repeat the todos
+    /// sharing its first characters on the next page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub id: Uuid,
+}
+
+impl Keyset {
+    pub fn of(todo: &Todo, sort: Sort) -> Self {
+        Self {
+            priority: todo.priority,
+            due_at: todo.due_at,
+            title: (sort == Sort::Title).then(|| todo.title.chars().take(KEYSET_TITLE).collect()),
+            id: todo.id,
+        }
+    }
+}
+
+/// Parsed `/todo/list` query, e.g. `@bob status:open tag:backend due:<friday sort:priority`
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub assignee: Option<SlackEscape>,
+    pub conditions: Vec<Condition>,
+    pub sort: Sort,
+}
+
+pub const USAGE: &str = "Usage: `/todo/list [@user] [status:open|done|all] [tag:name | #name] [due:<op><day>] [priority:<op><level>] [sort:priority|due|title]`\nDays can be `today`, `tomorrow`, a weekday, `2026-11-01`, `none` or `overdue`, operators are `<`, `<=`, `>`, `>=` and `=`.";
+
+pub mod compile;
+pub mod parse;
+pub mod token;
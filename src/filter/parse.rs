@@ -0,0 +1,207 @@
+use chrono::NaiveDate;
+
+use crate::{dates, tag::Tag, todo::Priority};
+
+use super::{
+    token::{tokenize, Token},
+    Comparison, Condition, DueValue, Filter, Sort, Status,
+};
+
+/// Parses a `/todo/list` query, the error explains which token was wrong
+pub fn parse(s: &str) -> Result<Filter, String> {
+    let mut filter = Filter::default();
+
+    for token in tokenize(s) {
+        match token {
+            Token::User(user) => {
+                if filter.assignee.is_some() {
+                    return Err("Only one user can be listed at a time".to_string());
+                }
+
+                filter.assignee = Some(user);
+            }
+            Token::Tag(tag) => filter.conditions.push(Condition::Tag(
+                Tag::normalize(&tag).ok_or(format!("`#{tag}` is not a valid tag"))?,
+            )),
+            Token::Field { key, op, value } => match key.as_str() {
+                "status" | "is" => {
+                    equals_only(&key, op)?;
+
+                    match value.to_lowercase().as_str() {
+                        "open" | "todo" => filter.conditions.push(Condition::Status(Status::Open)),
+                        "done" | "completed" => {
+                            filter.conditions.push(Condition::Status(Status::Done))
+                        }
+                        "all" => {}
+                        _ => {
+                            return Err(format!(
+                                "`{value}` is not a status, use `open`, `done` or `all`"
+                            ))
+                        }
+                    }
+                }
+                "tag" => filter.conditions.push(Condition::Tag(
+                    Tag::normalize(&value).ok_or(format!("`{value}` is not a valid tag"))?,
+                )),
+                "due" => {
+                    let due = match value.to_lowercase().as_str() {
+                        "none" => {
+                            equals_only(&key, op)?;
+                            DueValue::None
+                        }
+                        "overdue" => {
+                            equals_only(&key, op)?;
+                            DueValue::Overdue
+                        }
+                        day => {
+                            // Only validated here, the day is resolved in the user's timezone later
+                            dates::natural::parse_day(day, NaiveDate::default())
+                                .ok_or(format!("`{value}` is not a day I understand"))?;
+                            DueValue::Day(day.to_string())
+                        }
+                    };
+
+                    filter.conditions.push(Condition::Due(op, due));
+                }
+                "priority" | "p" => filter.conditions.push(Condition::Priority(
+                    op,
+                    value.parse::<Priority>().map_err(|_| format!(
+                        "`{value}` is not a priority, use `none`, `low`, `medium`, `high` or `urgent`"
+                    ))?,
+                )),
+                "sort" => {
+                    equals_only(&key, op)?;
+
+                    filter.sort = match value.to_lowercase().as_str() {
+                        "priority" => Sort::Priority,
+                        "due" => Sort::Due,
+                        "title" => Sort::Title,
+                        _ => {
+                            return Err(format!(
+                                "Can't sort by `{value}`, use `priority`, `due` or `title`"
+                            ))
+                        }
+                    };
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown filter `{key}:`, use `status:`, `tag:`, `due:`, `priority:` or `sort:`"
+                    ))
+                }
+            },
+            Token::Word(word) => {
+                return Err(format!(
+                    "I don't know what `{word}` means, filters look like `key:value`"
+                ))
+            }
+        }
+    }
+
+    Ok(filter)
+}
+
+fn equals_only(key: &str, op: Comparison) -> Result<(), String> {
+    match op {
+        Comparison::Eq => Ok(()),
+        _ => Err(format!("`{key}:` can't be compared with `<` or `>`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conditions(s: &str) -> Vec<Condition> {
+        parse(s).unwrap().conditions
+    }
+
+    #[test]
+    fn empty_query_lists_everything_by_priority() {
+        let filter = parse("").unwrap();
+
+        assert!(filter.assignee.is_none());
+        assert!(filter.conditions.is_empty());
+        assert_eq!(filter.sort, Sort::Priority);
+    }
+
+    #[test]
+    fn status() {
+        assert_eq!(
+            conditions("status:open"),
+            vec![Condition::Status(Status::Open)]
+        );
+        assert_eq!(conditions("is:done"), vec![Condition::Status(Status::Done)]);
+        assert_eq!(conditions("status:all"), vec![]);
+    }
+
+    #[test]
+    fn tags() {
+        assert_eq!(
+            conditions("#Backend tag:ops <#C1|infra>"),
+            vec![
+                Condition::Tag("backend".to_string()),
+                Condition::Tag("ops".to_string()),
+                Condition::Tag("infra".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn user() {
+        let filter = parse("<@U123|bob>").unwrap();
+        let user = filter.assignee.unwrap();
+
+        assert_eq!(user.id, "U123");
+        assert_eq!(user.display, "bob");
+    }
+
+    #[test]
+    fn due() {
+        assert_eq!(
+            conditions("due:none due:overdue due:<=friday due:>2026-11-01"),
+            vec![
+                Condition::Due(Comparison::Eq, DueValue::None),
+                Condition::Due(Comparison::Eq, DueValue::Overdue),
+                Condition::Due(Comparison::Le, DueValue::Day("friday".to_string())),
+                Condition::Due(Comparison::Gt, DueValue::Day("2026-11-01".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn priority() {
+        assert_eq!(
+            conditions("priority:>=high p:low"),
+            vec![
+                Condition::Priority(Comparison::Ge, Priority::High),
+                Condition::Priority(Comparison::Eq, Priority::Low),
+            ]
+        );
+    }
+
+    #[test]
+    fn sort() {
+        assert_eq!(parse("sort:due").unwrap().sort, Sort::Due);
+        assert_eq!(parse("sort:Title").unwrap().sort, Sort::Title);
+        assert_eq!(parse("sort:priority").unwrap().sort, Sort::Priority);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        for query in [
+            "status:maybe",
+            "status:<open",
+            "#!!!",
+            "due:someday",
+            "due:<none",
+            "priority:huge",
+            "sort:size",
+            "sort:>due",
+            "color:red",
+            "groceries",
+            "<@U1|a> <@U2|b>",
+        ] {
+            assert!(parse(query).is_err(), "{query}");
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::slack::escape::{unescape_html, SlackEscape};
+
+use super::Comparison;
+
+#[derive(Debug, Clone)]
+pub enum Token {
+    User(SlackEscape),
+    Tag(String),
+    Field {
+        key: String,
+        op: Comparison,
+        value: String,
+    },
+    Word(String),
+}
+
+/// Splits a query into tokens, undoing the html escaping slack applies to `<`, `>` and `&`
+pub fn tokenize(s: &str) -> Vec<Token> {
+    s.split_whitespace().map(token).collect()
+}
+
+fn token(s: &str) -> Token {
+    if let Some(user) = SlackEscape::user(s) {
+        return Token::User(user);
+    }
+
+    // Slack escapes tags that match a channel name, the display is still the tag
+    if let Some(channel) = SlackEscape::channel(s) {
+        return Token::Tag(channel.display);
+    }
+
+    let s = unescape_html(s);
+
+    if let Some(tag) = s.strip_prefix('#') {
+        return Token::Tag(tag.to_string());
+    }
+
+    match s.split_once(':') {
+        Some((key, value)) if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphabetic()) => {
+            let (op, value) = comparison(value);
+
+            Token::Field {
+                key: key.to_lowercase(),
+                op,
+                value: value.to_string(),
+            }
+        }
+        _ => Token::Word(s),
+    }
+}
+
+fn comparison(value: &str) -> (Comparison, &str) {
+    for (prefix, op) in [
+        ("<=", Comparison::Le),
+        (">=", Comparison::Ge),
+        ("<", Comparison::Lt),
+        (">", Comparison::Gt),
+        ("=", Comparison::Eq),
+    ] {
+        if let Some(value) = value.strip_prefix(prefix) {
+            return (op, value);
+        }
+    }
+
+    (Comparison::Eq, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_users_tags_fields_and_words() {
+        let tokens = tokenize("<@U123|bob> #backend <#C1|infra> due:&lt;=friday status:open oops");
+
+        assert!(
+            matches!(&tokens[0], Token::User(user) if user.id == "U123" && user.display == "bob")
+        );
+        assert!(matches!(&tokens[1], Token::Tag(tag) if tag == "backend"));
+        assert!(matches!(&tokens[2], Token::Tag(tag) if tag == "infra"));
+        assert!(matches!(
+            &tokens[3],
+            Token::Field { key, op: Comparison::Le, value } if key == "due" && value == "friday"
+        ));
+        assert!(matches!(
+            &tokens[4],
+            Token::Field { key, op: Comparison::Eq, value } if key == "status" && value == "open"
+        ));
+        assert!(matches!(&tokens[5], Token::Word(word) if word == "oops"));
+    }
+
+    #[test]
+    fn reads_every_comparison() {
+        for (value, expected) in [
+            ("<monday", Comparison::Lt),
+            ("<=monday", Comparison::Le),
+            (">monday", Comparison::Gt),
+            (">=monday", Comparison::Ge),
+            ("=monday", Comparison::Eq),
+            ("monday", Comparison::Eq),
+        ] {
+            assert_eq!(comparison(value), (expected, "monday"), "{value}");
+        }
+    }
+
+    #[test]
+    fn lowercases_keys_and_needs_an_alphabetic_key() {
+        assert!(matches!(&token("Status:done"), Token::Field { key, .. } if key == "status"));
+        assert!(matches!(&token(":tada:"), Token::Word(_)));
+        assert!(matches!(&token("v2:beta"), Token::Word(_)));
+    }
+}
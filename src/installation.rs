@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{postgres::PgQueryResult, PgPool};
+
+/// The bot token of a workspace the app is installed to. Org wide installs have an empty
+/// `team_id`, single workspaces outside an org have an empty `enterprise_id`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Installation {
+    pub team_id: String,
+    pub enterprise_id: String,
+    pub team_name: Option<String>,
+    pub bot_token: String,
+    pub bot_user_id: Option<String>,
+    pub scope: Option<String>,
+    pub installed_by: Option<String>,
+    pub installed_at: DateTime<Utc>,
+}
+
+impl Installation {
+    /// Reads the installation from an `oauth.v2.access` response
+    pub fn from_oauth(res: &Value) -> Option<Self> {
+        let enterprise_install = res["is_enterprise_install"].as_bool().unwrap_or(false);
+
+        Some(Self {
+            team_id: if enterprise_install {
+                String::new()
+            } else {
+                res["team"]["id"].as_str()?.to_string()
+            },
+            enterprise_id: res["enterprise"]["id"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            team_name: res["team"]["name"]
+                .as_str()
+                .or(res["enterprise"]["name"].as_str())
+                .map(str::to_string),
+            bot_token: res["access_token"].as_str()?.to_string(),
+            bot_user_id: res["bot_user_id"].as_str().map(str::to_string),
+            scope: res["scope"].as_str().map(str::to_string),
+            installed_by: res["authed_user"]["id"].as_str().map(str::to_string),
+            installed_at: Utc::now(),
+        })
+    }
+
+    /// Inserts the installation, reinstalling replaces the token
+    pub async fn save(&self, db: &PgPool) -> Result<PgQueryResult, sqlx::Error> {
+        sqlx::query(
+            r#"INSERT INTO installations (team_id, enterprise_id, team_name, bot_token, bot_user_id, scope, installed_by, installed_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (enterprise_id, team_id) DO UPDATE SET team_name = $3, bot_token = $4, bot_user_id = $5, scope = $6, installed_by = $7, installed_at = $8"#,
+        )
+        .bind(&self.team_id)
+        .bind(&self.enterprise_id)
+        .bind(&self.team_name)
+        .bind(&self.bot_token)
+        .bind(&self.bot_user_id)
+        .bind(&self.scope)
+        .bind(&self.installed_by)
+        .bind(self.installed_at)
+        .execute(db)
+        .await
+    }
+
+    /// Installation serving a workspace, either its own or the org wide one of its enterprise
+    pub async fn fetch(
+        team_id: &str,
+        enterprise_id: Option<&str>,
+        db: &PgPool,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<sqlx::Postgres, Installation>(
+            r#"SELECT * FROM installations WHERE (team_id = $1 AND team_id <> '') OR (team_id = '' AND enterprise_id = $2 AND enterprise_id <> '')
+            ORDER BY team_id DESC LIMIT 1"#,
+        )
+        .bind(team_id)
+        .bind(enterprise_id.unwrap_or_default())
+        .fetch_optional(db)
+        .await
+    }
+}
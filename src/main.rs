@@ -1,18 +1,63 @@
 use std::env;
 
 use dotenvy::dotenv;
-use slack::SlackApp;
+use installation::Installation;
+use slack::{oauth::SlackOAuth, SlackApp};
 use sqlx::{migrate, postgres::PgPoolOptions, types::chrono, PgPool};
+use tenant::Tenant;
 
 mod action;
+mod dates;
+mod filter;
+mod installation;
+mod message;
+mod preferences;
+mod quickadd;
+mod recurrence;
+mod reminder;
 mod router;
+mod scheduler;
 mod slack;
+mod tag;
+mod tenant;
 mod todo;
 
 #[derive(Clone)]
 pub struct ServerState {
     db: PgPool,
     slack: SlackApp,
+    signing_secret: String,
+    oauth: Option<SlackOAuth>,
+    tenant: Tenant,
+    /// Workspace the `SLACK_TOKEN` belongs to, according to `auth.test` at startup
+    token_team: Option<String>,
+}
+
+impl ServerState {
+    /// The state for a request from `team_id`, scoped to its workspace and with a slack client
+    /// using the bot token of its installation. Without one the `SLACK_TOKEN` client is kept when
+    /// the token belongs to `team_id`, for single workspace setups
+    pub async fn for_team(&self, team_id: &str, enterprise_id: Option<&str>) -> Self {
+        let mut state = self.clone();
+        state.tenant = Tenant::new(team_id, enterprise_id);
+
+        match Installation::fetch(team_id, enterprise_id, &self.db).await {
+            Ok(Some(installation)) => state.slack = self.slack.with_token(installation.bot_token),
+            Ok(None) if self.token_team.as_deref() == Some(team_id) => {}
+            Ok(None) => {
+                println!("The app isn't installed to {team_id}");
+                state.slack = self.slack.with_token(String::new());
+            }
+            Err(err) => {
+                println!("Failed to fetch the installation of {team_id}! {err}");
+                if self.token_team.as_deref() != Some(team_id) {
+                    state.slack = self.slack.with_token(String::new());
+                }
+            }
+        }
+
+        state
+    }
 }
 
 #[tokio::main]
@@ -40,26 +85,64 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .expect("An error occured while running migrations");
     println!("OK!");
 
-    // Run slack app
-    let slack = slack::SlackApp::new();
-    let res = slack
-        .send_message(
-            format!(
-                "[START]: {} - v{} ({})",
-                env!("CARGO_PKG_NAME"),
-                env!("CARGO_PKG_VERSION"),
-                chrono::Utc::now().to_rfc3339()
-            ),
-            env::var("SLACK_LOG_CHANNEL")
-                .expect("SLACK_LOG_CHANNEL environment vairable not found"),
-        )
-        .await;
+    // Run slack app, workspaces installed through OAuth get their own token per request
+    let slack = slack::SlackApp::new(env::var("SLACK_TOKEN").unwrap_or_default());
+    let mut token_team = None;
+    if slack.has_token() {
+        let res = slack
+            .send_message(
+                format!(
+                    "[START]: {} - v{} ({})",
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION"),
+                    chrono::Utc::now().to_rfc3339()
+                ),
+                env::var("SLACK_LOG_CHANNEL")
+                    .expect("SLACK_LOG_CHANNEL environment vairable not found"),
+            )
+            .await;
 
-    match res {
-        Ok(_) => {}
-        Err(err) => println!("An error occured while sending start message: {err}"),
+        match res {
+            Ok(_) => {}
+            Err(err) => println!("An error occured while sending start message: {err}"),
+        }
+
+        // Rows from before workspaces were tracked belong to the workspace of the token
+        match slack.auth_test().await {
+            Ok(v) => {
+                let tenant = Tenant::new(
+                    v.data["team_id"].as_str().unwrap_or_default(),
+                    v.data["enterprise_id"].as_str(),
+                );
+
+                match tenant.claim_unscoped(&pool).await {
+                    Ok(0) => {}
+                    Ok(n) => println!("Moved {n} rows into workspace {}", tenant.team_id),
+                    Err(err) => println!("Failed to scope existing rows! {err}"),
+                }
+
+                token_team = Some(tenant.team_id);
+            }
+            Err(err) => println!("An error occured while checking the slack token: {err}"),
+        }
     }
 
+    let state = ServerState {
+        db: pool,
+        slack,
+        // An empty secret would make signatures anyone can compute
+        signing_secret: env::var("SLACK_SIGNING_SECRET")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .expect("Can't find SLACK_SIGNING_SECRET environment variable, is it there?"),
+        oauth: SlackOAuth::from_env(),
+        tenant: Tenant::default(),
+        token_team,
+    };
+
+    // Run background jobs
+    tokio::spawn(scheduler::run(state.clone()));
+
     // Run axum server
     let server = format!(
         "{}:{}",
@@ -71,12 +154,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let listener = tokio::net::TcpListener::bind(&server)
         .await
         .expect("An error occured while creating TCP Listener");
-    axum::serve(
-        listener,
-        router::get_router().with_state(ServerState { db: pool, slack }),
-    )
-    .await
-    .expect("An error occured while running axum server");
+    axum::serve(listener, router::get_router(state))
+        .await
+        .expect("An error occured while running axum server");
 
     Ok(())
 }
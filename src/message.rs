@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{postgres::PgQueryResult, PgPool};
+use uuid::Uuid;
+
+use crate::ServerState;
+
+/// A posted slack message showing a todo, so reactions to it can update the todo
+#[derive(Debug, Clone, Default, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TodoMessage {
+    pub channel: String,
+    pub ts: String,
+    pub todo_id: Uuid,
+}
+
+impl TodoMessage {
+    /// Remembers that the message `ts` in `channel` shows the todos `todo_ids`
+    pub async fn insert_many(
+        channel: &str,
+        ts: &str,
+        todo_ids: &[Uuid],
+        db: &PgPool,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        sqlx::query(
+            r#"INSERT INTO todo_messages (channel, ts, todo_id) SELECT $1, $2, UNNEST($3::uuid[]) ON CONFLICT DO NOTHING"#,
+        )
+        .bind(channel)
+        .bind(ts)
+        .bind(todo_ids)
+        .execute(db)
+        .await
+    }
+
+    /// Ids of the todos shown in a message
+    pub async fn fetch_todos(
+        channel: &str,
+        ts: &str,
+        db: &PgPool,
+    ) -> Result<Vec<Uuid>, sqlx::Error> {
+        sqlx::query_scalar(r#"SELECT todo_id FROM todo_messages WHERE channel = $1 AND ts = $2"#)
+            .bind(channel)
+            .bind(ts)
+            .fetch_all(db)
+            .await
+    }
+}
+
+/// Posts a message showing a single todo and remembers it, so the todo can be completed by
+/// reacting to it
+pub async fn post(state: &ServerState, channel: String, todo_id: Uuid, block: &mut Value) {
+    post_many(state, channel, &[todo_id], block).await;
+}
+
+/// Posts a message showing the todos `todo_ids` and remembers it, so reacting to it completes
+/// every one of them
+pub async fn post_many(state: &ServerState, channel: String, todo_ids: &[Uuid], block: &mut Value) {
+    let res = match state.slack.send_block(channel, block).await {
+        Ok(v) => v.data,
+        Err(err) => {
+            println!("An error occured while posting a todo message. {err}");
+            return;
+        }
+    };
+
+    let (channel, ts) = match (res["channel"].as_str(), res["ts"].as_str()) {
+        (Some(channel), Some(ts)) => (channel, ts),
+        _ => {
+            println!("Posted todo message response did not contain a channel and ts");
+            return;
+        }
+    };
+
+    match TodoMessage::insert_many(channel, ts, todo_ids, &state.db).await {
+        Ok(_) => {}
+        Err(err) => println!("An error occured recording a todo message. {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::todo::Todo;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn insert_many_records_every_todo_once(db: PgPool) {
+        let mut ids = Vec::new();
+        for title in ["Ship it", "Write docs"] {
+            let mut todo = Todo {
+                title: title.to_string(),
+                slack_user: "U1".to_string(),
+                ..Default::default()
+            };
+            todo.assign_id().insert(&db).await.unwrap();
+            ids.push(todo.id);
+        }
+
+        TodoMessage::insert_many("C1", "1.000", &ids, &db)
+            .await
+            .unwrap();
+        // Replacing a page records its todos again
+        TodoMessage::insert_many("C1", "1.000", &ids, &db)
+            .await
+            .unwrap();
+
+        let mut recorded = TodoMessage::fetch_todos("C1", "1.000", &db).await.unwrap();
+        recorded.sort();
+        ids.sort();
+        assert_eq!(recorded, ids);
+        assert!(TodoMessage::fetch_todos("C1", "2.000", &db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,234 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgQueryResult, PgPool};
+
+use crate::{dates, tenant::Tenant};
+
+/// How late a digest may still go out, after a restart for example
+const DIGEST_WINDOW: Duration = Duration::hours(2);
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Preferences {
+    pub slack_user: String,
+    pub team_id: String,
+    pub enterprise_id: String,
+    pub timezone: String,
+    pub digest_enabled: bool,
+    pub digest_time: NaiveTime,
+    pub digest_sent_on: Option<NaiveDate>,
+}
+
+impl Preferences {
+    pub fn new(slack_user: String, timezone: String, tenant: &Tenant) -> Self {
+        Self {
+            slack_user,
+            team_id: tenant.team_id.clone(),
+            enterprise_id: tenant.enterprise_id.clone(),
+            timezone,
+            digest_enabled: true,
+            digest_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(), // Unwrap kept; Hardcoded data
+            digest_sent_on: None,
+        }
+    }
+
+    pub async fn fetch(
+        user: &str,
+        tenant: &Tenant,
+        db: &PgPool,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<sqlx::Postgres, Preferences>(
+            r#"SELECT * FROM preferences WHERE slack_user = $1 AND team_id = $2 AND enterprise_id = $3"#,
+        )
+        .bind(user)
+        .bind(&tenant.team_id)
+        .bind(&tenant.enterprise_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Inserts or updates the preferences, the digest bookkeeping is left alone
+    pub async fn save(&self, db: &PgPool) -> Result<PgQueryResult, sqlx::Error> {
+        sqlx::query(
+            r#"INSERT INTO preferences (slack_user, timezone, digest_enabled, digest_time, team_id, enterprise_id) VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (team_id, enterprise_id, slack_user) DO UPDATE SET timezone = $2, digest_enabled = $3, digest_time = $4"#,
+        )
+        .bind(&self.slack_user)
+        .bind(&self.timezone)
+        .bind(self.digest_enabled)
+        .bind(self.digest_time)
+        .bind(&self.team_id)
+        .bind(&self.enterprise_id)
+        .execute(db)
+        .await
+    }
+
+    /// Users with open todos that don't have preferences yet, with their workspace
+    pub async fn fetch_missing_users(db: &PgPool) -> Result<Vec<(String, Tenant)>, sqlx::Error> {
+        let rows: Vec<(String, String, String)> = sqlx::query_as(
+            r#"SELECT DISTINCT t.slack_user, t.team_id, t.enterprise_id FROM todos t
+            LEFT JOIN preferences p ON p.slack_user = t.slack_user AND p.team_id = t.team_id AND p.enterprise_id = t.enterprise_id
+            WHERE p.slack_user IS NULL AND t.completed IS NOT TRUE AND t.deleted_at IS NULL"#,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(user, team_id, enterprise_id)| {
+                (
+                    user,
+                    Tenant {
+                        team_id,
+                        enterprise_id,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    /// Preferences of users whose digest time has come in their own timezone and who haven't
+    /// had that digest yet, with the date of the digest
+    pub async fn fetch_due_digests(
+        now: DateTime<Utc>,
+        db: &PgPool,
+    ) -> Result<Vec<(Self, NaiveDate)>, sqlx::Error> {
+        let prefs = sqlx::query_as::<sqlx::Postgres, Preferences>(
+            r#"SELECT * FROM preferences WHERE digest_enabled"#,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(prefs
+            .into_iter()
+            .filter_map(|prefs| {
+                let date = prefs.digest_due(now)?;
+                Some((prefs, date))
+            })
+            .collect())
+    }
+
+    /// Timezone of the user, UTC when the stored one isn't known to chrono_tz
+    pub fn tz(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+
+    /// Date of the digest that is due at `now` and hasn't been sent yet. The window of a digest
+    /// late in the evening runs past midnight, so yesterday's digest is checked too
+    pub fn digest_due(&self, now: DateTime<Utc>) -> Option<NaiveDate> {
+        let tz = self.tz();
+        let today = now.with_timezone(&tz).date_naive();
+
+        [today.pred_opt()?, today].into_iter().find(|date| {
+            let at = dates::resolve(*date, self.digest_time, tz);
+
+            at <= now
+                && now < at + DIGEST_WINDOW
+                && self.digest_sent_on.is_none_or(|sent| sent < *date)
+        })
+    }
+
+    /// Records the digest of `date` as sent, returns false when it already was
+    pub async fn claim_digest(
+        &mut self,
+        date: NaiveDate,
+        db: &PgPool,
+    ) -> Result<bool, sqlx::Error> {
+        let res = sqlx::query(
+            r#"UPDATE preferences SET digest_sent_on = $1 WHERE slack_user = $2 AND team_id = $3 AND enterprise_id = $4 AND (digest_sent_on IS NULL OR digest_sent_on < $1)"#,
+        )
+        .bind(date)
+        .bind(&self.slack_user)
+        .bind(&self.team_id)
+        .bind(&self.enterprise_id)
+        .execute(db)
+        .await?;
+
+        self.digest_sent_on = Some(date);
+
+        Ok(res.rows_affected() == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefs(timezone: &str, time: &str) -> Preferences {
+        let mut prefs =
+            Preferences::new("U1".to_string(), timezone.to_string(), &Tenant::default());
+        prefs.digest_time = time.parse().unwrap();
+        prefs
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn digest_due_within_the_window() {
+        let prefs = prefs("Europe/Amsterdam", "09:00");
+
+        assert_eq!(prefs.digest_due(utc("2024-05-08T06:59:00Z")), None);
+        assert_eq!(
+            prefs.digest_due(utc("2024-05-08T07:00:00Z")),
+            Some(date("2024-05-08"))
+        );
+        assert_eq!(
+            prefs.digest_due(utc("2024-05-08T08:59:00Z")),
+            Some(date("2024-05-08"))
+        );
+        assert_eq!(prefs.digest_due(utc("2024-05-08T09:00:00Z")), None);
+    }
+
+    #[test]
+    fn digest_due_past_midnight() {
+        let mut prefs = prefs("Europe/Amsterdam", "23:30");
+
+        // 00:30 local on the 9th is still in the window of the digest of the 8th
+        assert_eq!(
+            prefs.digest_due(utc("2024-05-08T22:30:00Z")),
+            Some(date("2024-05-08"))
+        );
+
+        prefs.digest_sent_on = Some(date("2024-05-08"));
+        assert_eq!(prefs.digest_due(utc("2024-05-08T22:30:00Z")), None);
+        assert_eq!(
+            prefs.digest_due(utc("2024-05-09T21:30:00Z")),
+            Some(date("2024-05-09"))
+        );
+    }
+
+    #[test]
+    fn digest_due_once_per_day() {
+        let mut prefs = prefs("UTC", "09:00");
+        prefs.digest_sent_on = Some(date("2024-05-08"));
+
+        assert_eq!(prefs.digest_due(utc("2024-05-08T09:30:00Z")), None);
+    }
+
+    #[sqlx::test]
+    async fn invalid_timezone_only_affects_its_user(db: PgPool) {
+        let mut valid = prefs("Asia/Tokyo", "09:00");
+        valid.slack_user = "U1".to_string();
+        valid.save(&db).await.unwrap();
+
+        let mut invalid = prefs("Mars/Olympus_Mons", "09:00");
+        invalid.slack_user = "U2".to_string();
+        invalid.save(&db).await.unwrap();
+
+        // 09:30 in Tokyo, the invalid timezone falls back to UTC where it's 00:30
+        let due = Preferences::fetch_due_digests(utc("2024-05-08T00:30:00Z"), &db)
+            .await
+            .unwrap();
+        let users = due
+            .iter()
+            .map(|(prefs, date)| (prefs.slack_user.as_str(), *date))
+            .collect::<Vec<_>>();
+        assert_eq!(users, [("U1", date("2024-05-08"))]);
+    }
+}
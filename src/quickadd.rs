@@ -0,0 +1,167 @@
+use chrono::Utc;
+use chrono_tz::Tz;
+
+use crate::{
+    dates::natural,
+    slack::escape::{unescape_html, SlackEscape},
+    tag::Tag,
+    todo::Priority,
+};
+
+pub const USAGE: &str =
+    "Usage: `/todo/new <title> [!priority] [#tag] [due:<when>] [@user]`, e.g. `/todo/new Ship release notes !high #docs due:friday 3pm`";
+
+/// Most words a due date can take after `due:`, as in `due:next tue at 5pm`
+const DUE_WORDS: usize = 3;
+
+/// Inline syntax of `/todo/new`, e.g. `Ship release notes !high #docs due:tomorrow 17:00 @carol`
+#[derive(Debug, Clone, Default)]
+pub struct QuickAdd {
+    pub title: String,
+    pub priority: Option<Priority>,
+    pub tags: Vec<String>,
+    /// The due date as typed, resolved in the requesting user's timezone
+    pub due: Option<String>,
+    pub assignee: Option<SlackEscape>,
+}
+
+impl QuickAdd {
+    /// Picks the known tokens out of `text`, whatever is left becomes the title
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut quick = QuickAdd::default();
+        let mut title = Vec::new();
+        let words = text.split_whitespace().collect::<Vec<&str>>();
+        let mut i = 0;
+
+        while i < words.len() {
+            let word = words[i];
+            i += 1;
+
+            if let Some(user) = SlackEscape::user(word) {
+                if quick.assignee.is_some() {
+                    return Err("A todo can only be assigned to one user".to_string());
+                }
+
+                quick.assignee = Some(user);
+            } else if let Some(channel) = SlackEscape::channel(word) {
+                // Slack escapes tags that match a channel name, the display is still the tag
+                quick.tags.extend(Tag::normalize(&channel.display));
+            } else if let Some(tag) = word.strip_prefix('#').and_then(Tag::normalize) {
+                quick.tags.push(tag);
+            } else if let Some(priority) = word.strip_prefix('!').and_then(|v| v.parse().ok()) {
+                quick.priority = Some(priority);
+            } else if let Some(due) = word.strip_prefix("due:") {
+                // Dates can span a few words, `due:next tue 5pm`, take the longest one that parses
+                let (due, taken) = (0..=DUE_WORDS.min(words.len() - i))
+                    .rev()
+                    .map(|extra| {
+                        let due = std::iter::once(due)
+                            .chain(words[i..i + extra].iter().copied())
+                            .collect::<Vec<&str>>()
+                            .join(" ");
+
+                        (unescape_html(&due), extra)
+                    })
+                    .find(|(due, _)| natural::parse(due, Utc::now(), Tz::UTC).is_some())
+                    .ok_or(format!("`due:{due}` is not a date I understand"))?;
+
+                quick.due = Some(due);
+                i += taken;
+            } else {
+                title.push(unescape_html(word));
+            }
+        }
+
+        quick.title = title.join(" ");
+        quick.tags.sort();
+        quick.tags.dedup();
+
+        Ok(quick)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text, then the title, priority, tags and due date it parses to
+    type Case<'a> = (
+        &'a str,
+        &'a str,
+        Option<Priority>,
+        &'a [&'a str],
+        Option<&'a str>,
+    );
+
+    #[test]
+    fn parses_inline_tokens() {
+        let cases: &[Case] = &[
+            ("", "", None, &[], None),
+            ("   ", "", None, &[], None),
+            ("Ship it", "Ship it", None, &[], None),
+            (
+                "Ship release notes !high #docs due:friday 3pm",
+                "Ship release notes",
+                Some(Priority::High),
+                &["docs"],
+                Some("friday 3pm"),
+            ),
+            ("Learn C# basics", "Learn C# basics", None, &[], None),
+            ("Ping a#b and wow!", "Ping a#b and wow!", None, &[], None),
+            (
+                "Fix #Bug #bug #ops #bug",
+                "Fix",
+                None,
+                &["bug", "ops"],
+                None,
+            ),
+            ("Deploy !huge", "Deploy !huge", None, &[], None),
+            (
+                "Deploy !URGENT !low",
+                "Deploy",
+                Some(Priority::Low),
+                &[],
+                None,
+            ),
+            ("Lone # and !", "Lone # and !", None, &[], None),
+            (
+                "Check <#C1|infra> logs",
+                "Check logs",
+                None,
+                &["infra"],
+                None,
+            ),
+            (
+                "Fish &amp; chips due:tomorrow",
+                "Fish & chips",
+                None,
+                &[],
+                Some("tomorrow"),
+            ),
+        ];
+
+        for (text, title, priority, tags, due) in cases {
+            let quick = QuickAdd::parse(text).unwrap();
+
+            assert_eq!(quick.title, *title, "{text}");
+            assert_eq!(quick.priority, *priority, "{text}");
+            assert_eq!(quick.tags, *tags, "{text}");
+            assert_eq!(quick.due.as_deref(), *due, "{text}");
+        }
+    }
+
+    #[test]
+    fn takes_the_assignee() {
+        let quick = QuickAdd::parse("Review PR <@U123|carol>").unwrap();
+
+        assert_eq!(quick.title, "Review PR");
+        assert_eq!(quick.assignee.map(|v| v.id), Some("U123".to_string()));
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        for text in ["Review <@U1|a> <@U2|b>", "Ship due:someday", "Ship due:"] {
+            assert!(QuickAdd::parse(text).is_err(), "{text}");
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::dates;
+
+/// RRULE-style recurrence of a todo, stored as e.g. `FREQ=WEEKLY;BYDAY=MO,WE`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recurrence {
+    Daily,
+    Weekly(Vec<Weekday>),
+    Monthly(u32),
+    EveryNDays(u32),
+}
+
+impl Recurrence {
+    /// Next occurrence strictly after `after`, keeping the wall clock time in `tz`
+    pub fn next(&self, after: DateTime<Utc>, tz: Tz) -> DateTime<Utc> {
+        let local = after.with_timezone(&tz).naive_local();
+        let date = local.date();
+
+        let next = match self {
+            Recurrence::Daily => date + Duration::days(1),
+            Recurrence::EveryNDays(n) => date + Duration::days(i64::from(*n)),
+            Recurrence::Weekly(days) => (1..=7)
+                .map(|offset| date + Duration::days(offset))
+                .find(|d| days.is_empty() || days.contains(&d.weekday()))
+                .unwrap_or(date + Duration::weeks(1)),
+            Recurrence::Monthly(day) => {
+                let this = month_day(date.year(), date.month(), *day);
+
+                if this > date {
+                    this
+                } else if date.month() == 12 {
+                    month_day(date.year() + 1, 1, *day)
+                } else {
+                    month_day(date.year(), date.month() + 1, *day)
+                }
+            }
+        };
+
+        dates::resolve(next, local.time(), tz)
+    }
+
+    /// Human readable form, e.g. `weekly on Mon, Wed`
+    pub fn describe(&self) -> String {
+        match self {
+            Recurrence::Daily => "daily".to_string(),
+            Recurrence::EveryNDays(n) => format!("every {n} days"),
+            Recurrence::Monthly(day) => format!("monthly on day {day}"),
+            Recurrence::Weekly(days) => format!(
+                "weekly on {}",
+                days.iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "FREQ=DAILY"),
+            Recurrence::EveryNDays(n) => write!(f, "FREQ=DAILY;INTERVAL={n}"),
+            Recurrence::Monthly(day) => write!(f, "FREQ=MONTHLY;BYMONTHDAY={day}"),
+            Recurrence::Weekly(days) => write!(
+                f,
+                "FREQ=WEEKLY;BYDAY={}",
+                days.iter()
+                    .map(|d| d.to_string()[..2].to_uppercase())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut freq = None;
+        let mut interval = 1;
+        let mut days = Vec::new();
+        let mut month_day = None;
+
+        for part in s.split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or(format!("Invalid recurrence part `{part}`"))?;
+
+            match key.to_uppercase().as_str() {
+                "FREQ" => freq = Some(value.to_uppercase()),
+                "INTERVAL" => {
+                    interval = value
+                        .parse::<u32>()
+                        .map_err(|_| format!("Invalid interval `{value}`"))?
+                }
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        days.push(weekday(day).ok_or(format!("Invalid weekday `{day}`"))?);
+                    }
+                }
+                "BYMONTHDAY" => {
+                    month_day = Some(
+                        value
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|d| (1..=31).contains(d))
+                            .ok_or(format!("Invalid day of month `{value}`"))?,
+                    )
+                }
+                _ => return Err(format!("Unsupported recurrence part `{key}`")),
+            }
+        }
+
+        match freq.as_deref() {
+            Some("DAILY") if interval > 1 => Ok(Recurrence::EveryNDays(interval)),
+            Some("DAILY") => Ok(Recurrence::Daily),
+            Some("WEEKLY") => Ok(Recurrence::Weekly(days)),
+            Some("MONTHLY") => Ok(Recurrence::Monthly(
+                month_day.ok_or("Monthly recurrence needs BYMONTHDAY")?,
+            )),
+            Some(other) => Err(format!("Unsupported frequency `{other}`")),
+            None => Err("Recurrence needs a FREQ".to_string()),
+        }
+    }
+}
+
+/// Parses both RRULE (`MO`) and english (`mon`, `monday`) weekday names
+pub fn weekday(s: &str) -> Option<Weekday> {
+    let s = s.to_lowercase();
+
+    [
+        (Weekday::Mon, ["mo", "mon", "monday"]),
+        (Weekday::Tue, ["tu", "tue", "tuesday"]),
+        (Weekday::Wed, ["we", "wed", "wednesday"]),
+        (Weekday::Thu, ["th", "thu", "thursday"]),
+        (Weekday::Fri, ["fr", "fri", "friday"]),
+        (Weekday::Sat, ["sa", "sat", "saturday"]),
+        (Weekday::Sun, ["su", "sun", "sunday"]),
+    ]
+    .into_iter()
+    .find(|(_, names)| names.contains(&s.as_str()))
+    .map(|(day, _)| day)
+}
+
+/// Day `day` of a month, clamped to the last day for shorter months
+fn month_day(year: i32, month: u32, day: u32) -> NaiveDate {
+    (1..=day)
+        .rev()
+        .find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_and_prints_rules() {
+        for (rule, recurrence) in [
+            ("FREQ=DAILY", Recurrence::Daily),
+            ("FREQ=DAILY;INTERVAL=3", Recurrence::EveryNDays(3)),
+            (
+                "FREQ=WEEKLY;BYDAY=MO,WE",
+                Recurrence::Weekly(vec![Weekday::Mon, Weekday::Wed]),
+            ),
+            ("FREQ=MONTHLY;BYMONTHDAY=31", Recurrence::Monthly(31)),
+        ] {
+            assert_eq!(rule.parse::<Recurrence>(), Ok(recurrence.clone()));
+            assert_eq!(recurrence.to_string(), rule);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for rule in [
+            "",
+            "FREQ=YEARLY",
+            "FREQ=MONTHLY",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=WEEKLY;BYDAY=XX",
+            "FREQ=DAILY;INTERVAL=two",
+            "FREQ=DAILY;COUNT=3",
+            "DAILY",
+        ] {
+            assert!(rule.parse::<Recurrence>().is_err(), "{rule}");
+        }
+    }
+
+    #[test]
+    fn monthly_rolls_over_to_shorter_months() {
+        let monthly = Recurrence::Monthly(31);
+
+        assert_eq!(
+            monthly.next(utc("2024-01-31T09:00:00Z"), Tz::UTC),
+            utc("2024-02-29T09:00:00Z")
+        );
+        assert_eq!(
+            monthly.next(utc("2023-01-31T09:00:00Z"), Tz::UTC),
+            utc("2023-02-28T09:00:00Z")
+        );
+        assert_eq!(
+            monthly.next(utc("2024-02-29T09:00:00Z"), Tz::UTC),
+            utc("2024-03-31T09:00:00Z")
+        );
+        assert_eq!(
+            monthly.next(utc("2024-12-31T09:00:00Z"), Tz::UTC),
+            utc("2025-01-31T09:00:00Z")
+        );
+    }
+
+    #[test]
+    fn monthly_later_this_month() {
+        assert_eq!(
+            Recurrence::Monthly(15).next(utc("2024-05-03T09:00:00Z"), Tz::UTC),
+            utc("2024-05-15T09:00:00Z")
+        );
+    }
+
+    #[test]
+    fn weekly_on_days() {
+        // 2024-05-08 is a Wednesday
+        let weekly = Recurrence::Weekly(vec![Weekday::Mon, Weekday::Wed]);
+
+        assert_eq!(
+            weekly.next(utc("2024-05-06T09:00:00Z"), Tz::UTC),
+            utc("2024-05-08T09:00:00Z")
+        );
+        assert_eq!(
+            weekly.next(utc("2024-05-08T09:00:00Z"), Tz::UTC),
+            utc("2024-05-13T09:00:00Z")
+        );
+        assert_eq!(
+            Recurrence::Weekly(Vec::new()).next(utc("2024-05-08T09:00:00Z"), Tz::UTC),
+            utc("2024-05-09T09:00:00Z")
+        );
+    }
+
+    #[test]
+    fn keeps_wall_clock_time_across_dst() {
+        // Europe/Amsterdam switches to summer time on 2024-03-31
+        let tz: Tz = "Europe/Amsterdam".parse().unwrap();
+
+        assert_eq!(
+            Recurrence::Daily.next(utc("2024-03-30T08:00:00Z"), tz),
+            utc("2024-03-31T07:00:00Z")
+        );
+        assert_eq!(
+            Recurrence::EveryNDays(2).next(utc("2024-03-30T08:00:00Z"), tz),
+            utc("2024-04-01T07:00:00Z")
+        );
+    }
+
+    #[test]
+    fn parses_weekday_names() {
+        assert_eq!(weekday("MO"), Some(Weekday::Mon));
+        assert_eq!(weekday("thu"), Some(Weekday::Thu));
+        assert_eq!(weekday("Sunday"), Some(Weekday::Sun));
+        assert_eq!(weekday("someday"), None);
+    }
+}
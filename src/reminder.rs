@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Default, Copy, PartialEq, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "reminder_kind", rename_all = "lowercase")]
+pub enum ReminderKind {
+    #[default]
+    Upcoming = 0,
+    Overdue = 1,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, sqlx::FromRow)]
+pub struct Reminder {
+    pub id: Uuid,
+    pub todo_id: Uuid,
+    pub kind: ReminderKind,
+    pub due_at: DateTime<Utc>,
+    pub sent_at: DateTime<Utc>,
+}
+
+impl Reminder {
+    pub fn new(todo_id: Uuid, kind: ReminderKind, due_at: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            todo_id,
+            kind,
+            due_at,
+            sent_at: Utc::now(),
+        }
+    }
+
+    /// Records the reminder, returns false when it was already sent for this due date
+    pub async fn claim(&self, db: &PgPool) -> Result<bool, sqlx::Error> {
+        let res = sqlx::query(
+            r#"INSERT INTO reminders (id, todo_id, kind, due_at, sent_at) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (todo_id, kind, due_at) DO NOTHING"#,
+        )
+        .bind(self.id)
+        .bind(self.todo_id)
+        .bind(self.kind)
+        .bind(self.due_at)
+        .bind(self.sent_at)
+        .execute(db)
+        .await?;
+
+        Ok(res.rows_affected() == 1)
+    }
+}
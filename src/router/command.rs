@@ -0,0 +1,311 @@
+use std::{collections::HashMap, future::Future, pin::Pin};
+
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+    Form,
+};
+use serde_json::json;
+
+use crate::{
+    dates,
+    slack::{block::SlackBlock, escape::json_string, payloads::SlackCommand},
+    todo::Todo,
+    ServerState,
+};
+
+use super::{complete_todo, list, open_edit_modal, search, search::find_todo, snooze, todo_new};
+
+/// A slash command, with the state resolved for the workspace it comes from. `/todo` resolves
+/// it once and hands it on to the subcommand handlers
+pub struct Command(pub ServerState, pub SlackCommand);
+
+#[async_trait]
+impl FromRequest<ServerState> for Command {
+    type Rejection = <Form<SlackCommand> as FromRequest<ServerState>>::Rejection;
+
+    async fn from_request(req: Request, state: &ServerState) -> Result<Self, Self::Rejection> {
+        let Form(payload) = Form::<SlackCommand>::from_request(req, state).await?;
+        let state = state
+            .for_team(&payload.team_id, payload.enterprise_id.as_deref())
+            .await;
+
+        Ok(Self(state, payload))
+    }
+}
+
+/// Acknowledges a command right away and runs `handler` in the background. Slack shows
+/// `operation_timeout` when a command isn't answered within 3 seconds, the handlers reply
+/// through the `response_url` instead
+pub async fn background<F, Fut>(handler: F, command: Command)
+where
+    F: FnOnce(Command) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(handler(command));
+}
+
+type Handler = fn(ServerState, SlackCommand) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// A `/todo` subcommand, the handler gets the command with the subcommand word removed
+struct Subcommand {
+    name: &'static str,
+    args: &'static str,
+    description: &'static str,
+    run: Handler,
+}
+
+impl Subcommand {
+    fn usage(&self, command: &str) -> String {
+        format!("`{command} {}{}`", self.name, self.args)
+    }
+}
+
+/// Everything `/todo` can do, in the order `help` lists it
+static SUBCOMMANDS: &[Subcommand] = &[
+    Subcommand {
+        name: "add",
+        args: " [title] [!priority] [#tag] [@user] [due:<when>]",
+        description: "Create a todo, without a title the create modal opens",
+        run: |state, payload| Box::pin(todo_new(Command(state, payload))),
+    },
+    Subcommand {
+        name: "list",
+        args: " [@user] [status:open|done|all] [#tag] [due:<op><day>] [sort:priority|due|title]",
+        description: "List todos, filtered and sorted, `status:open` leaves out completed ones",
+        run: |state, payload| Box::pin(list::todo_list(Command(state, payload))),
+    },
+    Subcommand {
+        name: "done",
+        args: " <todo>",
+        description: "Complete the open todo that best matches the text",
+        run: |state, payload| Box::pin(todo_done(state, payload)),
+    },
+    Subcommand {
+        name: "edit",
+        args: " <todo>",
+        description: "Open the edit modal of the best matching todo",
+        run: |state, payload| Box::pin(todo_edit(state, payload)),
+    },
+    Subcommand {
+        name: "delete",
+        args: " <todo>",
+        description: "Move the best matching todo to the trash",
+        run: |state, payload| Box::pin(todo_delete(state, payload)),
+    },
+    Subcommand {
+        name: "search",
+        args: " <terms>",
+        description: "Full text search through titles and descriptions",
+        run: |state, payload| Box::pin(search::todo_search(Command(state, payload))),
+    },
+    Subcommand {
+        name: "snooze",
+        args: " <todo> until <when>",
+        description: "Push back the due date of the best matching todo",
+        run: |state, payload| Box::pin(snooze::todo_snooze(Command(state, payload))),
+    },
+    Subcommand {
+        name: "help",
+        args: "",
+        description: "Show this help",
+        run: |state, payload| Box::pin(help(state, payload, None)),
+    },
+];
+
+/// Single entry point dispatching on the first word of the text
+pub async fn todo_command(Command(state, mut payload): Command) {
+    let text = payload.text.trim().to_string();
+    let (name, rest) = text.split_once(char::is_whitespace).unwrap_or((&text, ""));
+    let name = name.to_lowercase();
+
+    match subcommand(&name) {
+        Some(sub) => {
+            payload.text = rest.trim().to_string();
+            (sub.run)(state, payload).await;
+        }
+        None if name.is_empty() => help(state, payload, None).await,
+        None => {
+            let warning = format!(":warning: `{name}` is not a subcommand");
+            help(state, payload, Some(warning)).await;
+        }
+    }
+}
+
+/// The subcommand called `name`, lowercase
+fn subcommand(name: &str) -> Option<&'static Subcommand> {
+    SUBCOMMANDS.iter().find(|sub| sub.name == name)
+}
+
+/// Usage and description of every subcommand, generated from the registry
+fn commands(command: &str) -> String {
+    SUBCOMMANDS
+        .iter()
+        .map(|sub| format!("{}\n{}", sub.usage(command), sub.description))
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+/// Usage of every subcommand, generated from the registry
+async fn help(state: ServerState, payload: SlackCommand, warning: Option<String>) {
+    let commands = commands(&payload.command);
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert(
+        "intro",
+        json_string(&warning.unwrap_or_else(|| {
+            "Manage your todos without leaving slack, they're also on my Home tab.".to_string()
+        })),
+    );
+    template.insert("commands", json_string(&commands));
+
+    let mut block = SlackBlock::new("help".to_string());
+    block.load().fill(template);
+
+    match state
+        .slack
+        .send_webhook(payload.response_url, &mut block.into(), true)
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+    }
+}
+
+async fn todo_done(state: ServerState, payload: SlackCommand) {
+    let text = match done(&payload, &state).await {
+        Ok(v) => v,
+        Err(err) => format!(":warning: {err}\nUsage: {}", usage("done", &payload)),
+    };
+
+    reply(&state, payload, text).await;
+}
+
+async fn done(payload: &SlackCommand, state: &ServerState) -> Result<String, String> {
+    let mut todo = find(payload, state).await?;
+
+    let next = match complete_todo(&mut todo, state).await {
+        Ok(v) => v,
+        Err(err) => {
+            println!("An error occured updating todo in the database. {err}");
+            return Err("Something went wrong completing the todo".to_string());
+        }
+    };
+
+    Ok(match next {
+        Some(next) => format!(
+            ":white_check_mark: Completed *{}*, the next one is due {}",
+            todo.title,
+            next.due_at.map(dates::slack_date).unwrap_or_default()
+        ),
+        None => format!(":white_check_mark: Completed *{}*", todo.title),
+    })
+}
+
+async fn todo_edit(state: ServerState, payload: SlackCommand) {
+    match find(&payload, &state).await {
+        Ok(todo) => {
+            open_edit_modal(
+                &state,
+                payload.trigger_id,
+                payload.user_id,
+                payload.channel_id,
+                &todo,
+            )
+            .await
+        }
+        Err(err) => {
+            let text = format!(":warning: {err}\nUsage: {}", usage("edit", &payload));
+            reply(&state, payload, text).await;
+        }
+    }
+}
+
+async fn todo_delete(state: ServerState, payload: SlackCommand) {
+    let text = match delete(&payload, &state).await {
+        Ok(v) => v,
+        Err(err) => format!(":warning: {err}\nUsage: {}", usage("delete", &payload)),
+    };
+
+    reply(&state, payload, text).await;
+}
+
+async fn delete(payload: &SlackCommand, state: &ServerState) -> Result<String, String> {
+    let mut todo = find(payload, state).await?;
+
+    match todo.delete(&state.db).await {
+        Ok(_) => {}
+        Err(err) => {
+            println!("An error occured deleting todo from the database. {err}");
+            return Err("Something went wrong deleting the todo".to_string());
+        }
+    }
+
+    Ok(format!(
+        ":wastebasket: Moved *{}* to the trash, restore it with `/todo/trash`",
+        todo.title
+    ))
+}
+
+/// Open todo of the caller that best matches the text of the command
+async fn find(payload: &SlackCommand, state: &ServerState) -> Result<Todo, String> {
+    if payload.text.is_empty() {
+        return Err("Say which todo you mean".to_string());
+    }
+
+    match find_todo(&payload.user_id, &payload.text, &state.tenant, &state.db).await {
+        Ok(Some(v)) => Ok(v),
+        Ok(None) => Err(format!("No open todo matches `{}`", payload.text)),
+        Err(err) => {
+            println!("Failed to search todos! {err}");
+            Err("Something went wrong looking for the todo".to_string())
+        }
+    }
+}
+
+fn usage(name: &str, payload: &SlackCommand) -> String {
+    SUBCOMMANDS
+        .iter()
+        .find(|sub| sub.name == name)
+        .map(|sub| sub.usage(&payload.command))
+        .unwrap_or_default()
+}
+
+async fn reply(state: &ServerState, payload: SlackCommand, text: String) {
+    match state
+        .slack
+        .send_webhook(payload.response_url, &mut json!({"text": text}), true)
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_subcommand_dispatches_and_has_help() {
+        let help = commands("/todo");
+
+        for sub in SUBCOMMANDS {
+            assert_eq!(subcommand(sub.name).map(|v| v.name), Some(sub.name));
+            assert!(
+                help.contains(&format!("`/todo {}", sub.name)),
+                "{} is missing from the help",
+                sub.name
+            );
+            assert!(help.contains(sub.description));
+        }
+
+        for name in [
+            "add", "list", "done", "edit", "delete", "search", "snooze", "help",
+        ] {
+            assert!(subcommand(name).is_some(), "{name} isn't registered");
+        }
+        assert!(subcommand("").is_none());
+        assert!(subcommand("frobnicate").is_none());
+    }
+}
@@ -0,0 +1,255 @@
+use axum::{extract::State, Json};
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::{
+    message::TodoMessage,
+    slack::payloads::{
+        SlackAppHomeOpenedEvent, SlackEvent, SlackEventCallback, SlackEventRequest,
+        SlackLinkSharedEvent, SlackMemberJoinedChannelEvent, SlackMessageEvent, SlackReactionEvent,
+    },
+    todo::Todo,
+    ServerState,
+};
+
+use super::{complete_todo, home};
+
+/// Reacting with this to a posted todo completes it, removing the reaction reopens it
+const DONE_REACTION: &str = "white_check_mark";
+
+/// Events API requests. Slack retries events that aren't acknowledged within 3 seconds, so
+/// they are handled in the background and only the url verification challenge is answered
+pub async fn slack_events(
+    State(state): State<ServerState>,
+    Json(payload): Json<SlackEventRequest>,
+) -> String {
+    match payload {
+        SlackEventRequest::UrlVerification { challenge } => challenge,
+        SlackEventRequest::EventCallback(callback) => {
+            tokio::spawn(dispatch(*callback, state));
+            String::new()
+        }
+        SlackEventRequest::Unknown => {
+            println!("Unhandled event request type");
+            String::new()
+        }
+    }
+}
+
+async fn dispatch(callback: SlackEventCallback, state: ServerState) {
+    let state = state
+        .for_team(&callback.team_id, callback.enterprise_id.as_deref())
+        .await;
+
+    match &callback.event {
+        SlackEvent::AppHomeOpened(event) => app_home_opened(event, &state).await,
+        SlackEvent::ReactionAdded(event) => reaction(event, &state, true).await,
+        SlackEvent::ReactionRemoved(event) => reaction(event, &state, false).await,
+        SlackEvent::Message(event) if event.channel_type == "im" => message_im(event, &state).await,
+        SlackEvent::Message(_) => {}
+        SlackEvent::LinkShared(event) => link_shared(event, &state).await,
+        SlackEvent::MemberJoinedChannel(event) => {
+            member_joined_channel(event, &callback, &state).await
+        }
+        SlackEvent::Unknown => println!("Unhandled event {}", callback.event_id),
+    }
+}
+
+async fn app_home_opened(event: &SlackAppHomeOpenedEvent, state: &ServerState) {
+    // The event also fires for the messages tab, which isn't ours to render
+    if event.tab != "home" {
+        return;
+    }
+
+    home::publish(&event.user, state).await;
+}
+
+/// Completes or reopens the todos of a posted message when its owner reacts to it
+async fn reaction(event: &SlackReactionEvent, state: &ServerState, added: bool) {
+    if event.reaction != DONE_REACTION || event.item.r#type != "message" {
+        return;
+    }
+
+    let ids = match TodoMessage::fetch_todos(&event.item.channel, &event.item.ts, &state.db).await {
+        Ok(v) => v,
+        Err(err) => {
+            println!("Failed to fetch the todos of a message! {err}");
+            return;
+        }
+    };
+
+    let mut changed = Vec::new();
+    for id in ids {
+        let mut todo = match Todo::fetch(id, &state.tenant, &state.db).await {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Failed to fetch todo! {err}");
+                continue;
+            }
+        };
+
+        if !todo.can_update(&event.user) || todo.completed == added || todo.deleted_at.is_some() {
+            continue;
+        }
+
+        let res = if added {
+            complete_todo(&mut todo, state).await.map(|_| ())
+        } else {
+            todo.set_completed(false, &state.db).await.map(|_| ())
+        };
+
+        match res {
+            Ok(_) => changed.push(todo.slack_user),
+            Err(err) => println!("An error occured updating todo in the database. {err}"),
+        }
+    }
+
+    changed.sort();
+    changed.dedup();
+    for user in changed {
+        home::publish(&user, state).await;
+    }
+}
+
+/// Direct messages to the bot get pointed at the command and the Home tab
+async fn message_im(event: &SlackMessageEvent, state: &ServerState) {
+    // Edits, joins and the bot's own replies come in as messages too
+    if event.user.is_none() || event.subtype.is_some() || event.bot_id.is_some() {
+        return;
+    }
+
+    let text = "Add todos with `/todo add <title>`, `/todo help` lists everything I can do. \
+        Your todos are on my Home tab.";
+
+    reply(state, &event.channel, text.to_string()).await;
+}
+
+/// Unfurls links to todos, `https://<app domain>/todos/<id>`, into the todo itself. Only the
+/// todos the sharing user may update are unfurled, so a link doesn't show anyone else's
+async fn link_shared(event: &SlackLinkSharedEvent, state: &ServerState) {
+    let mut unfurls = Map::new();
+    for link in &event.links {
+        let id = match todo_link(&link.url) {
+            Some(v) => v,
+            None => continue,
+        };
+
+        let mut todo = match Todo::fetch(id, &state.tenant, &state.db).await {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Failed to fetch todo! {err}");
+                continue;
+            }
+        };
+
+        if !todo.can_update(&event.user) || todo.deleted_at.is_some() {
+            continue;
+        }
+
+        unfurls.insert(link.url.clone(), json!({"blocks": todo.blocks()}));
+    }
+
+    if unfurls.is_empty() {
+        return;
+    }
+
+    let mut body = match (&event.unfurl_id, &event.source) {
+        (Some(unfurl_id), Some(source)) => json!({"unfurl_id": unfurl_id, "source": source}),
+        _ => json!({"channel": event.channel, "ts": event.message_ts}),
+    };
+    body["unfurls"] = Value::Object(unfurls);
+
+    match state.slack.unfurl(&body).await {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while unfurling todo links. {err}"),
+    }
+}
+
+/// Id of the todo a link points at, the last segment of a `/todos/<id>` path
+fn todo_link(url: &str) -> Option<Uuid> {
+    let path = url.split(['?', '#']).next()?.trim_end_matches('/');
+    let (rest, id) = path.rsplit_once('/')?;
+    if !rest.ends_with("/todos") {
+        return None;
+    }
+
+    Uuid::parse_str(id).ok()
+}
+
+/// Introduces the bot when it's added to a channel
+async fn member_joined_channel(
+    event: &SlackMemberJoinedChannelEvent,
+    callback: &SlackEventCallback,
+    state: &ServerState,
+) {
+    if !callback
+        .authorizations
+        .iter()
+        .any(|auth| auth.is_bot && auth.user_id == event.user)
+    {
+        return;
+    }
+
+    let text = ":wave: Hi! I keep track of todos. Add one with `/todo add`, \
+        your todos are on my Home tab.";
+
+    reply(state, &event.channel, text.to_string()).await;
+}
+
+async fn reply(state: &ServerState, channel: &str, text: String) {
+    match state.slack.send_message(text, channel.to_string()).await {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while sending a message. {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn todo_links() {
+        let id = "6f1c1a52-9f4e-4a5b-8c1d-2f0e3b9a7c11";
+        let parsed = Some(Uuid::parse_str(id).unwrap());
+
+        assert_eq!(
+            todo_link(&format!("https://todos.example.com/todos/{id}")),
+            parsed
+        );
+        assert_eq!(
+            todo_link(&format!("https://todos.example.com/todos/{id}/")),
+            parsed
+        );
+        assert_eq!(
+            todo_link(&format!("https://todos.example.com/todos/{id}?ref=x#top")),
+            parsed
+        );
+
+        assert_eq!(
+            todo_link(&format!("https://todos.example.com/tags/{id}")),
+            None
+        );
+        assert_eq!(todo_link("https://todos.example.com/todos/not-an-id"), None);
+        assert_eq!(todo_link("https://todos.example.com/todos"), None);
+    }
+
+    #[test]
+    fn link_shared_is_a_typed_event() {
+        let event: SlackEvent = serde_json::from_value(json!({
+            "type": "link_shared",
+            "channel": "C123",
+            "user": "U123",
+            "message_ts": "1700000000.000100",
+            "links": [{"domain": "todos.example.com", "url": "https://todos.example.com/todos/1"}]
+        }))
+        .unwrap();
+
+        match event {
+            SlackEvent::LinkShared(event) => {
+                assert_eq!(event.links[0].domain, "todos.example.com");
+                assert_eq!(event.unfurl_id, None);
+            }
+            other => panic!("Expected link_shared, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::{
+    dates,
+    slack::{block::SlackBlock, payloads::SlackBlockActionsData},
+    todo::{Todo, TODO_SELECT},
+    ServerState,
+};
+
+/// Home tabs are limited to 100 blocks, each todo takes two of them
+const HOME_TODOS: i64 = 40;
+
+/// Renders the todo dashboard of `user` and publishes it to their Home tab
+pub async fn publish(user: &str, state: &ServerState) {
+    let query = sqlx::query_as::<sqlx::Postgres, Todo>(&format!(
+        r#"{TODO_SELECT} WHERE t.slack_user = $1 AND t.team_id = $3 AND t.enterprise_id = $4
+        AND NOT t.completed AND t.deleted_at IS NULL
+        ORDER BY t.due_at ASC NULLS LAST, t.priority DESC, t.title LIMIT $2"#
+    ))
+    .bind(user)
+    .bind(HOME_TODOS)
+    .bind(&state.tenant.team_id)
+    .bind(&state.tenant.enterprise_id)
+    .fetch_all(&state.db)
+    .await;
+
+    let todos = match query {
+        Ok(v) => v,
+        Err(err) => {
+            println!("Failed to fetch todos for the home tab! {err}");
+            return;
+        }
+    };
+
+    let tz = dates::user_timezone(&state.slack, user).await;
+    let today = dates::today(tz);
+
+    let mut overdue = Vec::new();
+    let mut due_today = Vec::new();
+    let mut upcoming = Vec::new();
+    let mut no_date = Vec::new();
+
+    for todo in todos {
+        match todo.due_at {
+            None => no_date.push(todo),
+            Some(_) if todo.is_overdue() => overdue.push(todo),
+            Some(due) if due.with_timezone(&tz).date_naive() == today => due_today.push(todo),
+            Some(_) => upcoming.push(todo),
+        }
+    }
+
+    let total = overdue.len() + due_today.len() + upcoming.len() + no_date.len();
+
+    let sections = [
+        (":warning: Overdue", overdue),
+        (":alarm_clock: Today", due_today),
+        (":calendar: Upcoming", upcoming),
+        (":spiral_note_pad: No date", no_date),
+    ]
+    .into_iter()
+    .filter(|(_, todos)| !todos.is_empty())
+    .map(|(heading, mut todos)| section(heading, &mut todos))
+    .collect::<String>();
+
+    let summary = match total {
+        0 => "Nothing to do, you're all caught up :tada:".to_string(),
+        n if n as i64 == HOME_TODOS => {
+            format!("Showing your first {n} open todos, use `/todo/list` to see the rest")
+        }
+        1 => "1 open todo".to_string(),
+        n => format!("{n} open todos"),
+    };
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("sections", sections);
+    template.insert(
+        "summary",
+        format!("{summary} · Updated {}", dates::slack_date(Utc::now())),
+    );
+
+    let mut block = SlackBlock::new("home".to_string());
+    block.load().fill(template);
+
+    match state.slack.publish_view(user, &block.into()).await {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while publishing the home tab. {err}"),
+    }
+}
+
+/// Heading and todo blocks of one group on the Home tab, followed by a comma
+fn section(heading: &str, todos: &mut [Todo]) -> String {
+    let list = todos
+        .iter_mut()
+        .map(|todo| todo.block().data)
+        .collect::<Vec<String>>()
+        .join(",");
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("heading", heading.to_string());
+    template.insert("list", list);
+
+    let mut block = SlackBlock::new("home-section".to_string());
+    block.load().fill(template);
+
+    format!("{},", block.data)
+}
+
+/// "New todo" button of the Home tab. There is no channel to confirm in, so the action is
+/// recorded without one and the Home tab is republished once the todo is created
+pub async fn new_todo(payload: &SlackBlockActionsData, state: &ServerState) {
+    super::open_create_modal(
+        state,
+        payload.trigger_id.clone(),
+        payload.user.id.clone(),
+        String::new(),
+        HashMap::new(),
+    )
+    .await;
+}
+
+/// Whether a block action was clicked on a view, which is the Home tab for non modal buttons
+pub fn from_view(payload: &SlackBlockActionsData) -> bool {
+    payload.container["type"].as_str() == Some("view") && payload.response_url.is_none()
+}
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+
+use super::command::Command;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{
+    dates,
+    filter::{parse::parse, Comparison, Filter, Keyset, USAGE},
+    message::{self, TodoMessage},
+    slack::{
+        block::SlackBlock,
+        payloads::{SlackBlockActionsData, SlackInteractionAction},
+    },
+    todo::{Todo, TODO_SELECT},
+    ServerState,
+};
+
+const PAGE_SIZE: i64 = 5;
+
+/// Page of a `/todo/list` listing, carried in the value of the Next/Previous buttons. Pages
+/// start after or end before a todo, so they don't shift when todos are added or completed
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ListCursor {
+    pub text: String,
+    pub user_id: String,
+    pub user_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<Keyset>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<Keyset>,
+}
+
+pub async fn todo_list(Command(state, payload): Command) {
+    let cursor = ListCursor {
+        text: payload.text,
+        user_id: payload.user_id,
+        user_name: payload.user_name,
+        ..Default::default()
+    };
+
+    let filter = match parse(&cursor.text) {
+        Ok(v) => v,
+        Err(err) => {
+            match state
+                .slack
+                .send_webhook(
+                    payload.response_url,
+                    &mut json!({"text": format!(":warning: {err}\n{USAGE}")}),
+                    true,
+                )
+                .await
+            {
+                Ok(_) => {}
+                Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+            }
+
+            return;
+        }
+    };
+
+    let blocks = match list_page(&cursor, &filter, &state).await {
+        Ok(v) => v,
+        Err(err) => {
+            println!("Failed to fetch todos! {err}");
+            return;
+        }
+    };
+
+    match blocks {
+        Some((mut blocks, todo_ids)) => {
+            message::post_many(&state, payload.channel_id, &todo_ids, &mut blocks).await;
+        }
+        None => {
+            match state
+                .slack
+                .send_message(
+                    format!("No todos found for <@{}>", list_display(&cursor, &filter)),
+                    payload.channel_id.clone(),
+                )
+                .await
+            {
+                Ok(_) => {}
+                Err(err) => println!("An error occured while sending a slack message. {err}"),
+            };
+        }
+    }
+}
+
+/// Replaces a listing in place with the page carried by a Next/Previous button
+pub async fn list_page_action(
+    payload: &SlackBlockActionsData,
+    action: &SlackInteractionAction,
+    state: &ServerState,
+) {
+    let cursor: ListCursor = match serde_json::from_str(action.value.as_deref().unwrap_or_default())
+    {
+        Ok(v) => v,
+        Err(err) => {
+            println!("Block action did not contain a valid list cursor! {err}");
+            return;
+        }
+    };
+
+    let response_url = match &payload.response_url {
+        Some(v) => v.clone(),
+        None => {
+            println!("Block action did not contain a response url");
+            return;
+        }
+    };
+
+    let filter = match parse(&cursor.text) {
+        Ok(v) => v,
+        Err(err) => {
+            println!("List cursor contained an invalid query! {err}");
+            return;
+        }
+    };
+
+    let (blocks, todo_ids) = match list_page(&cursor, &filter, state).await {
+        Ok(Some(v)) => v,
+        Ok(None) => (json!([]), Vec::new()),
+        Err(err) => {
+            println!("Failed to fetch todos! {err}");
+            return;
+        }
+    };
+
+    match state
+        .slack
+        .send_webhook(
+            response_url,
+            &mut json!({"blocks": blocks, "replace_original": true}),
+            false,
+        )
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => {
+            println!("An error occured while sending a slack webhook: {err}");
+            return;
+        }
+    }
+
+    // The page replaces the message in place, so reactions to it now complete its todos
+    let (channel, ts) = match (
+        payload.container["channel_id"].as_str(),
+        payload.container["message_ts"].as_str(),
+    ) {
+        (Some(channel), Some(ts)) => (channel, ts),
+        _ => return,
+    };
+
+    match TodoMessage::insert_many(channel, ts, &todo_ids, &state.db).await {
+        Ok(_) => {}
+        Err(err) => println!("An error occured recording a todo message. {err}"),
+    }
+}
+
+/// Display name of whose todos a listing shows
+fn list_display(cursor: &ListCursor, filter: &Filter) -> String {
+    filter
+        .assignee
+        .as_ref()
+        .map(|user| user.display.clone())
+        .unwrap_or_else(|| cursor.user_name.clone())
+}
+
+/// Renders the list blocks for the page at `cursor` with the ids of the todos on it, None when
+/// no todos match at all
+async fn list_page(
+    cursor: &ListCursor,
+    filter: &Filter,
+    state: &ServerState,
+) -> Result<Option<(Value, Vec<Uuid>)>, sqlx::Error> {
+    let tz = if filter.needs_timezone() {
+        dates::user_timezone(&state.slack, &cursor.user_id).await
+    } else {
+        Tz::UTC
+    };
+
+    let mut todos = fetch_page(cursor, filter, tz, state).await?;
+
+    // The todos around the cursor may all be gone since the buttons were rendered
+    let mut cursor = cursor.clone();
+    if todos.is_empty() && (cursor.after.is_some() || cursor.before.is_some()) {
+        cursor.after = None;
+        cursor.before = None;
+        todos = fetch_page(&cursor, filter, tz, state).await?;
+    }
+
+    // One todo more than a page is fetched to know whether there is another page
+    let more = todos.len() as i64 > PAGE_SIZE;
+    todos.truncate(PAGE_SIZE as usize);
+    if cursor.before.is_some() {
+        todos.reverse();
+    }
+
+    let (first, last) = match (todos.first(), todos.last()) {
+        (Some(first), Some(last)) => (
+            Keyset::of(first, filter.sort),
+            Keyset::of(last, filter.sort),
+        ),
+        _ => return Ok(None),
+    };
+
+    let mut count = QueryBuilder::<Postgres>::new("SELECT COUNT(*), COUNT(*) FILTER (WHERE ");
+    filter.push_keyset(&mut count, Comparison::Lt, &first);
+    count.push(") FROM todos t");
+    filter.push_where(&mut count, &cursor.user_id, &state.tenant, tz);
+
+    let (total, skipped): (i64, i64) = count.build_query_as().fetch_one(&state.db).await?;
+
+    let list = todos
+        .iter_mut()
+        .map(|todo| todo.block().data)
+        .collect::<Vec<String>>()
+        .join(",");
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("list", list);
+    template.insert("user", list_display(&cursor, filter));
+    template.insert(
+        "showing",
+        format!(
+            "Showing {}–{} of {}",
+            skipped + 1,
+            skipped + todos.len() as i64,
+            total
+        ),
+    );
+
+    let (previous, next) = match (&cursor.after, &cursor.before) {
+        (_, Some(_)) => (more, true),
+        (Some(_), None) => (true, more),
+        (None, None) => (false, more),
+    };
+    template.insert(
+        "pagination",
+        pagination(&cursor, previous.then_some(first), next.then_some(last)),
+    );
+
+    let mut block = SlackBlock::new("list".to_string());
+    block.load().fill(template).trim();
+
+    let todo_ids = todos.iter().map(|todo| todo.id).collect();
+    Ok(Some((block.into(), todo_ids)))
+}
+
+/// Todos of the page at `cursor` in listing order, or reversed when going back, with one more
+/// todo than fits on a page when there is one
+async fn fetch_page(
+    cursor: &ListCursor,
+    filter: &Filter,
+    tz: Tz,
+    state: &ServerState,
+) -> Result<Vec<Todo>, sqlx::Error> {
+    let mut page = QueryBuilder::<Postgres>::new(TODO_SELECT);
+    filter.push_where(&mut page, &cursor.user_id, &state.tenant, tz);
+
+    if let Some(after) = &cursor.after {
+        page.push(" AND ");
+        filter.push_keyset(&mut page, Comparison::Gt, after);
+    }
+    if let Some(before) = &cursor.before {
+        page.push(" AND ");
+        filter.push_keyset(&mut page, Comparison::Lt, before);
+    }
+
+    filter.push_order(&mut page, cursor.before.is_some());
+    page.push(" LIMIT ");
+    page.push_bind(PAGE_SIZE + 1);
+
+    page.build_query_as::<Todo>().fetch_all(&state.db).await
+}
+
+/// Previous/Next buttons for a page, left out when everything fits on one page
+fn pagination(cursor: &ListCursor, previous: Option<Keyset>, next: Option<Keyset>) -> String {
+    let mut buttons = Vec::new();
+
+    if let Some(first) = previous {
+        buttons.push(page_button(
+            ListCursor {
+                after: None,
+                before: Some(first),
+                ..cursor.clone()
+            },
+            "Previous :arrow_left:",
+        ));
+    }
+
+    if let Some(last) = next {
+        buttons.push(page_button(
+            ListCursor {
+                after: Some(last),
+                before: None,
+                ..cursor.clone()
+            },
+            "Next :arrow_right:",
+        ));
+    }
+
+    if buttons.is_empty() {
+        return String::new();
+    }
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("buttons", buttons.join(","));
+
+    let mut block = SlackBlock::new("pagination".to_string());
+    block.load().fill(template);
+
+    format!("{},", block.data)
+}
+
+fn page_button(cursor: ListCursor, text: &str) -> String {
+    json!({
+        "type": "button",
+        "action_id": "todo-list-page",
+        "text": {"type": "plain_text", "text": text, "emoji": true},
+        "value": serde_json::to_string(&cursor).unwrap_or_default()
+    })
+    .to_string()
+}
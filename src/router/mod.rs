@@ -2,136 +2,388 @@ use std::collections::HashMap;
 
 use axum::{
     extract::State,
+    middleware,
     routing::{get, post},
     Form, Router,
 };
-use sqlx::query;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use serde_json::{json, Value};
 
 use crate::{
-    action::{self, Action, ActionType},
+    action::{Action, ActionType},
+    dates, message,
+    quickadd::{self, QuickAdd},
+    recurrence::{self, Recurrence},
     slack::{
         block::SlackBlock,
-        escape::SlackEscape,
+        escape::json_string,
         modal::SlackModal,
-        payloads::{SlackCommand, SlackInteraction, SlackInteractionData},
+        payloads::{
+            SlackBlockActionsData, SlackInteraction, SlackInteractionAction, SlackInteractionData,
+            SlackMessageActionData, SlackShortcutData,
+        },
     },
-    todo::Todo,
+    tag::Tag,
+    todo::{Priority, Todo, TODO_SELECT},
     ServerState,
 };
 
-pub fn get_router() -> Router<ServerState> {
+use self::command::{background, Command};
+
+pub fn get_router(state: ServerState) -> Router {
     Router::new()
-        .route("/", get(root))
-        .route("/todo/new", post(todo_new))
-        .route("/todo/list", post(todo_list))
+        .route(
+            "/todo",
+            post(|command: Command| background(command::todo_command, command)),
+        )
+        .route(
+            "/todo/new",
+            post(|command: Command| background(todo_new, command)),
+        )
+        .route(
+            "/todo/list",
+            post(|command: Command| background(list::todo_list, command)),
+        )
+        .route(
+            "/todo/search",
+            post(|command: Command| background(search::todo_search, command)),
+        )
+        .route(
+            "/todo/snooze",
+            post(|command: Command| background(snooze::todo_snooze, command)),
+        )
+        .route(
+            "/todo/trash",
+            post(|command: Command| background(todo_trash, command)),
+        )
         .route("/slack/interactivity", post(slack_interactivity))
+        .route("/slack/events", post(events::slack_events))
+        // Only slack calls the routes above, so they all need a valid signature
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            verify::verify_slack,
+        ))
+        .route("/", get(root))
+        .route("/slack/install", get(oauth::slack_install))
+        .route("/slack/oauth/callback", get(oauth::slack_oauth_callback))
+        .with_state(state)
 }
 
 async fn root() -> &'static str {
     "Hello, Slack To-Do!"
 }
 
-async fn todo_new(State(state): State<ServerState>, Form(payload): Form<SlackCommand>) {
-    if payload.text.trim().is_empty() {
-        let mut template: HashMap<&str, String> = HashMap::new();
-        template.insert("initial_channel", payload.channel_id.clone());
-
-        let mut modal = SlackModal::new("create".to_string(), payload.trigger_id);
-        modal.load().fill(template);
+async fn todo_new(Command(state, payload): Command) {
+    let quick = match QuickAdd::parse(&payload.text) {
+        Ok(v) => v,
+        Err(err) => {
+            match state
+                .slack
+                .send_webhook(
+                    payload.response_url,
+                    &mut json!({"text": format!(":warning: {err}\n{}", quickadd::USAGE)}),
+                    true,
+                )
+                .await
+            {
+                Ok(_) => {}
+                Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+            }
 
-        match state.slack.open_modal(&modal).await {
-            Ok(v) => {
-                let id = match v["view"]["id"].as_str() {
-                    Some(v) => v,
-                    None => {
-                        println!("ID not found in payload");
-                        return;
-                    }
-                };
+            return;
+        }
+    };
 
-                let mut action = Action::new(
-                    ActionType::CreateModal,
-                    id.to_string(),
-                    payload.user_id,
-                    payload.channel_id,
-                );
+    if quick.title.is_empty() {
+        let tz = dates::user_timezone(&state.slack, &payload.user_id).await;
+        let template = quick_add_template(quick, Utc::now(), tz);
 
-                match action.assign_id().insert(&state.db).await {
-                    Ok(_) => {}
-                    Err(err) => println!("Action to database insertion error! {err}"),
-                }
-            }
-            Err(err) => println!("An error occured while openning a modal. {err}"),
-        }
+        open_create_modal(
+            &state,
+            payload.trigger_id,
+            payload.user_id,
+            payload.channel_id,
+            template,
+        )
+        .await;
 
         return;
     }
 
+    let todo = match quick_add(&state, quick, payload.user_id).await {
+        Some(v) => v,
+        None => return,
+    };
+
+    let block = created_block(&todo);
+
+    match state
+        .slack
+        .send_webhook(payload.response_url, &mut block.into(), true)
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+    }
+}
+
+/// Creates the todo described by a quick-add text, on behalf of `user`
+async fn quick_add(state: &ServerState, quick: QuickAdd, user: String) -> Option<Todo> {
+    let due_at = match &quick.due {
+        Some(due) => {
+            let tz = dates::user_timezone(&state.slack, &user).await;
+            dates::natural::parse(due, Utc::now(), tz)
+        }
+        None => None,
+    };
+
     let mut todo = Todo {
-        title: payload.text,
-        slack_user: payload.user_id,
-        ..Default::default()
+        title: quick.title,
+        slack_user: quick.assignee.map(|v| v.id).unwrap_or_else(|| user.clone()),
+        created_by: Some(user),
+        priority: quick.priority.unwrap_or_default(),
+        tags: quick.tags,
+        due_at,
+        ..Todo::new(&state.tenant)
     };
     match todo.assign_id().insert(&state.db).await {
         Ok(_) => {}
         Err(err) => {
             println!("An error occured inserting todo to the database. {err}");
-            return;
+            return None;
         }
     }
 
+    match Tag::assign(todo.id, &todo.tags, &state.db).await {
+        Ok(_) => {}
+        Err(err) => println!("An error occured assigning tags to the todo. {err}"),
+    }
+
+    notify_assignee(state, &mut todo).await;
+
+    Some(todo)
+}
+
+/// Prefills the create modal with what a quick-add text without a title did have
+fn quick_add_template(
+    quick: QuickAdd,
+    now: DateTime<Utc>,
+    tz: Tz,
+) -> HashMap<&'static str, String> {
+    let mut template: HashMap<&str, String> = HashMap::new();
+    if let Some(assignee) = quick.assignee {
+        template.insert("user", assignee.id);
+    }
+
+    // Slack only takes an initial option that is exactly one of the options of the modal
+    let label = match quick.priority.unwrap_or_default() {
+        Priority::None => None,
+        Priority::Low => Some((":large_blue_circle: Low", "low")),
+        Priority::Medium => Some((":large_yellow_circle: Medium", "medium")),
+        Priority::High => Some((":red_circle: High", "high")),
+        Priority::Urgent => Some((":rotating_light: Urgent", "urgent")),
+    };
+    if let Some((text, value)) = label {
+        let option = json!({
+            "text": {"type": "plain_text", "text": text, "emoji": true},
+            "value": value
+        });
+        template.insert(
+            "initial_priority",
+            format!(r#""initial_option": {option},"#),
+        );
+    }
+
+    let tags = quick
+        .tags
+        .iter()
+        .map(|tag| format!("#{tag}"))
+        .collect::<Vec<String>>()
+        .join(" ");
+    template.insert("tags", json_string(&tags));
+
+    let due_at = quick
+        .due
+        .as_deref()
+        .and_then(|due| dates::natural::parse(due, now, tz));
+    if let Some(due_at) = due_at {
+        let local = due_at.with_timezone(&tz);
+        template.insert(
+            "initial_date",
+            format!(r#""initial_date": "{}","#, local.format("%Y-%m-%d")),
+        );
+        template.insert(
+            "initial_time",
+            format!(r#""initial_time": "{}","#, local.format("%H:%M")),
+        );
+    }
+
+    template
+}
+
+/// Confirmation for a created todo, showing everything that was picked up besides the title
+fn created_block(todo: &Todo) -> SlackBlock {
     let mut template: HashMap<&str, String> = HashMap::new();
-    template.insert("title", todo.title);
-    template.insert("desc", todo.description.unwrap_or_default());
+    template.insert("title", json_string(&todo.title));
+    template.insert(
+        "desc",
+        json_string(todo.description.as_deref().unwrap_or_default()),
+    );
+    template.insert("summary", todo.summary());
 
     let mut block = SlackBlock::new("created".to_string());
     block.load().fill(template);
 
-    state
-        .slack
-        .send_webhook(payload.response_url, &mut block.into(), true)
-        .await;
+    block
 }
 
-async fn todo_list(State(state): State<ServerState>, Form(payload): Form<SlackCommand>) {
-    let target = if payload.text.len() == 0 {
-        SlackEscape {
-            id: payload.user_id.clone(),
-            display: payload.user_name.clone(),
+/// Opens the create modal and records it as an action, so its submission can be handled.
+/// `template` can prefill the modal, the assignee defaults to `user`
+async fn open_create_modal(
+    state: &ServerState,
+    trigger_id: String,
+    user: String,
+    channel: String,
+    mut template: HashMap<&str, String>,
+) {
+    let tags = match Tag::fetch_user(&user, &state.tenant, &state.db).await {
+        Ok(v) => v,
+        Err(err) => {
+            println!("Failed to fetch tags! {err}");
+            Vec::new()
         }
-    } else {
-        SlackEscape::from(payload.text)
     };
 
-    let query = sqlx::query_as::<sqlx::Postgres, Todo>(
-        r#"SELECT * FROM todos WHERE slack_user = $1 LIMIT 5"#,
-    )
-    .bind(target.id)
+    // Slack rejects an empty initial conversation, so it's left out without a channel
+    template.insert(
+        "initial_conversation",
+        if channel.is_empty() {
+            String::new()
+        } else {
+            format!(r#""initial_conversation": "{channel}","#)
+        },
+    );
+    template.entry("user").or_insert_with(|| user.clone());
+    template.entry("desc").or_default();
+    template.entry("source").or_default();
+    template.entry("initial_priority").or_default();
+    template.entry("tags").or_default();
+    template.entry("initial_date").or_default();
+    template.entry("initial_time").or_default();
+    template.insert("tag_select", tag_select(&tags));
+
+    let mut modal = SlackModal::new("create".to_string(), trigger_id);
+    modal.load().fill(template);
+
+    match state.slack.open_modal(&modal).await {
+        Ok(v) => {
+            let id = match v.data["view"]["id"].as_str() {
+                Some(v) => v,
+                None => {
+                    println!("ID not found in payload");
+                    return;
+                }
+            };
+
+            let mut action = Action::new(
+                ActionType::CreateModal,
+                id.to_string(),
+                user,
+                channel,
+                &state.tenant,
+            );
+
+            match action.assign_id().insert(&state.db).await {
+                Ok(_) => {}
+                Err(err) => println!("Action to database insertion error! {err}"),
+            }
+        }
+        Err(err) => println!("An error occured while openning a modal. {err}"),
+    }
+}
+
+/// DMs the assignee of a todo created by someone else
+async fn notify_assignee(state: &ServerState, todo: &mut Todo) {
+    let by = match &todo.created_by {
+        Some(by) if *by != todo.slack_user => by.clone(),
+        _ => return,
+    };
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("user", by);
+    template.insert("todo", todo.block().data);
+
+    let mut block = SlackBlock::new("assigned".to_string());
+    block.load().fill(template).trim();
+
+    message::post(state, todo.slack_user.clone(), todo.id, &mut block.into()).await;
+}
+
+/// Picker for already used tags, slack rejects selects without options so it's left out then
+fn tag_select(tags: &[Tag]) -> String {
+    if tags.is_empty() {
+        return String::new();
+    }
+
+    let options = tags
+        .iter()
+        .map(|tag| {
+            json!({
+                "text": {"type": "plain_text", "text": format!("#{}", tag.name)},
+                "value": tag.name
+            })
+            .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("options", options);
+
+    let mut block = SlackBlock::new("tag-select".to_string());
+    block.load().fill(template);
+
+    format!("{},", block.data)
+}
+
+async fn todo_trash(Command(state, payload): Command) {
+    let mut data = match trash_message(&payload.user_id, &state).await {
+        Some(v) => v,
+        None => return,
+    };
+
+    match state
+        .slack
+        .send_webhook(payload.response_url, &mut data, true)
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+    }
+}
+
+async fn trash_message(user: &str, state: &ServerState) -> Option<Value> {
+    let query = sqlx::query_as::<sqlx::Postgres, Todo>(&format!(
+        r#"{TODO_SELECT} WHERE t.slack_user = $1 AND t.team_id = $2 AND t.enterprise_id = $3 AND t.deleted_at IS NOT NULL
+        ORDER BY t.deleted_at DESC LIMIT 10"#
+    ))
+    .bind(user)
+    .bind(&state.tenant.team_id)
+    .bind(&state.tenant.enterprise_id)
     .fetch_all(&state.db)
     .await;
 
     let mut query = match query {
         Ok(v) => v,
         Err(err) => {
-            println!("Failed to fetch todos! {err}");
-            return;
+            println!("Failed to fetch deleted todos! {err}");
+            return None;
         }
     };
 
-    if query.len() == 0 {
-        match state
-            .slack
-            .send_message(
-                format!("No todos found for <@{}>", target.display),
-                payload.channel_id.clone(),
-            )
-            .await
-        {
-            Ok(_) => {}
-            Err(err) => println!("An error occured while sending a slack message. {err}"),
-        };
-
-        return;
+    if query.is_empty() {
+        return Some(json!({"text": "Your trash is empty :wastebasket:"}));
     }
 
     let todos = query
@@ -142,24 +394,20 @@ async fn todo_list(State(state): State<ServerState>, Form(payload): Form<SlackCo
 
     let mut template: HashMap<&str, String> = HashMap::new();
     template.insert("list", todos);
-    template.insert("user", target.display);
 
-    let mut block = SlackBlock::new("list".to_string());
-    block.load().fill(template).trim();
+    let mut block = SlackBlock::new("trash".to_string());
+    block.load().fill(template);
 
-    state
-        .slack
-        .send_block(payload.channel_id, &mut block.into())
-        .await;
+    Some(block.into())
 }
 
+/// Interactivity requests. Slack shows `operation_timeout` when they aren't acknowledged within
+/// 3 seconds, so they are handled in the background
 async fn slack_interactivity(
     State(state): State<ServerState>,
     Form(interaction): Form<SlackInteraction>,
 ) {
-    let payload = serde_json::from_str(&interaction.payload);
-
-    let payload: SlackInteractionData = match payload {
+    let payload: Value = match serde_json::from_str(&interaction.payload) {
         Ok(v) => v,
         Err(err) => {
             println!("Couldn't extract payload from slack: {err}");
@@ -167,16 +415,488 @@ async fn slack_interactivity(
         }
     };
 
-    if payload.r#type == "view_submission" {
-        let action = Action::fetch_slack_id(payload.view.id.clone(), &state.db)
+    tokio::spawn(handle_interaction(payload, state));
+}
+
+async fn handle_interaction(payload: Value, state: ServerState) {
+    let state = state
+        .for_team(
+            payload["team"]["id"].as_str().unwrap_or_default(),
+            payload["enterprise"]["id"].as_str(),
+        )
+        .await;
+
+    match payload["type"].as_str().unwrap_or_default() {
+        "view_submission" => {
+            let payload: SlackInteractionData = match serde_json::from_value(payload) {
+                Ok(v) => v,
+                Err(err) => {
+                    println!("Couldn't extract view submission from slack: {err}");
+                    return;
+                }
+            };
+
+            let action =
+                match Action::fetch_slack_id(payload.view.id.clone(), &state.tenant, &state.db)
+                    .await
+                {
+                    Ok(v) => v,
+                    Err(err) => {
+                        println!(
+                            "Couldn't find the action of view {}! {err}",
+                            payload.view.id
+                        );
+                        return;
+                    }
+                };
+
+            match action.r#type {
+                ActionType::CreateModal => create_modal(&payload, &state, &action).await,
+                ActionType::EditModal => edit_modal(&payload, &state, &action).await,
+                ActionType::PreferencesModal => {
+                    preferences::preferences_modal(&payload, &state, &action).await
+                }
+            }
+        }
+        "block_actions" => {
+            let payload: SlackBlockActionsData = match serde_json::from_value(payload) {
+                Ok(v) => v,
+                Err(err) => {
+                    println!("Couldn't extract block actions from slack: {err}");
+                    return;
+                }
+            };
+
+            for action in payload.actions.iter() {
+                match action.action_id.as_str() {
+                    "todo-complete" => todo_toggle(&payload, action, &state, true).await,
+                    "todo-undo" => todo_toggle(&payload, action, &state, false).await,
+                    "todo-edit" => todo_edit(&payload, action, &state).await,
+                    "home-new-todo" => home::new_todo(&payload, &state).await,
+                    "digest-preferences" => preferences::open_modal(&payload, &state).await,
+                    "todo-list-page" => list::list_page_action(&payload, action, &state).await,
+                    "todo-delete" => todo_delete(&payload, action, &state).await,
+                    "todo-restore" => todo_trash_action(&payload, action, &state, false).await,
+                    "todo-purge" => todo_trash_action(&payload, action, &state, true).await,
+                    "reminder-complete" => reminder_action(&payload, action, &state, None).await,
+                    "reminder-snooze-hour" => {
+                        reminder_action(&payload, action, &state, Some(Duration::hours(1))).await
+                    }
+                    "reminder-snooze-day" => {
+                        reminder_action(&payload, action, &state, Some(Duration::days(1))).await
+                    }
+                    other => println!("Unhandled block action: {other}"),
+                }
+            }
+
+            // Views have no response url to update through, render the Home tab again instead
+            if home::from_view(&payload) {
+                home::publish(&payload.user.id, &state).await;
+            }
+        }
+        "message_action" => {
+            let payload: SlackMessageActionData = match serde_json::from_value(payload) {
+                Ok(v) => v,
+                Err(err) => {
+                    println!("Couldn't extract message action from slack: {err}");
+                    return;
+                }
+            };
+
+            shortcuts::message_action(&payload, &state).await;
+        }
+        "shortcut" => {
+            let payload: SlackShortcutData = match serde_json::from_value(payload) {
+                Ok(v) => v,
+                Err(err) => {
+                    println!("Couldn't extract shortcut from slack: {err}");
+                    return;
+                }
+            };
+
+            shortcuts::global_shortcut(&payload, &state).await;
+        }
+        other => println!("Unhandled interaction type: {other}"),
+    }
+}
+
+async fn todo_toggle(
+    payload: &SlackBlockActionsData,
+    action: &SlackInteractionAction,
+    state: &ServerState,
+    completed: bool,
+) {
+    let mut todo = match fetch_action_todo(action, state).await {
+        Some(v) => v,
+        None => return,
+    };
+
+    if !is_owner(payload, &todo, state).await {
+        return;
+    }
+
+    let res = if completed {
+        complete_todo(&mut todo, state).await
+    } else {
+        todo.set_completed(false, &state.db).await.map(|_| None)
+    };
+
+    let next = match res {
+        Ok(v) => v,
+        Err(err) => {
+            println!("An error occured updating todo in the database. {err}");
+            return;
+        }
+    };
+
+    let response_url = match &payload.response_url {
+        Some(v) => v.clone(),
+        None => return,
+    };
+
+    if let Some(next) = next {
+        let text = format!(
+            ":repeat: Next *{}* is due {}",
+            next.title,
+            next.due_at.map(dates::slack_date).unwrap_or_default()
+        );
+
+        match state
+            .slack
+            .send_webhook(
+                response_url.clone(),
+                &mut json!({"text": text, "replace_original": false}),
+                true,
+            )
             .await
-            .unwrap();
+        {
+            Ok(_) => {}
+            Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+        }
+    }
+
+    let blocks = replace_todo_blocks(&payload.message, todo.id, todo.blocks());
 
-        match action.r#type {
-            ActionType::CreateModal => create_modal(&payload, &state, &action).await,
-            _ => unreachable!(),
+    match state
+        .slack
+        .send_webhook(
+            response_url,
+            &mut json!({"blocks": blocks, "replace_original": true}),
+            false,
+        )
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+    }
+}
+
+async fn todo_edit(
+    payload: &SlackBlockActionsData,
+    action: &SlackInteractionAction,
+    state: &ServerState,
+) {
+    let todo = match fetch_action_todo(action, state).await {
+        Some(v) => v,
+        None => return,
+    };
+
+    if !is_owner(payload, &todo, state).await {
+        return;
+    }
+
+    open_edit_modal(
+        state,
+        payload.trigger_id.clone(),
+        payload.user.id.clone(),
+        payload.channel.clone().unwrap_or_default().id,
+        &todo,
+    )
+    .await;
+}
+
+/// Opens the edit modal of `todo` and records it as an action, so its submission can be handled
+async fn open_edit_modal(
+    state: &ServerState,
+    trigger_id: String,
+    user: String,
+    channel: String,
+    todo: &Todo,
+) {
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("id", todo.id.to_string());
+    template.insert("title", json_string(&todo.title));
+    template.insert(
+        "desc",
+        json_string(todo.description.as_deref().unwrap_or_default()),
+    );
+
+    let mut modal = SlackModal::new("edit".to_string(), trigger_id);
+    modal.load().fill(template);
+
+    match state.slack.open_modal(&modal).await {
+        Ok(v) => {
+            let id = match v.data["view"]["id"].as_str() {
+                Some(v) => v,
+                None => {
+                    println!("ID not found in payload");
+                    return;
+                }
+            };
+
+            let mut action = Action::new(
+                ActionType::EditModal,
+                id.to_string(),
+                user,
+                channel,
+                &state.tenant,
+            );
+
+            match action.assign_id().insert(&state.db).await {
+                Ok(_) => {}
+                Err(err) => println!("Action to database insertion error! {err}"),
+            }
         }
+        Err(err) => println!("An error occured while openning a modal. {err}"),
+    }
+}
+
+async fn fetch_action_todo(action: &SlackInteractionAction, state: &ServerState) -> Option<Todo> {
+    let id = match action.value.as_deref().map(uuid::Uuid::parse_str) {
+        Some(Ok(v)) => v,
+        _ => {
+            println!("Block action did not contain a valid todo id!");
+            return None;
+        }
+    };
+
+    match Todo::fetch(id, &state.tenant, &state.db).await {
+        Ok(v) => Some(v),
+        Err(err) => {
+            println!("Failed to fetch todo! {err}");
+            None
+        }
+    }
+}
+
+/// Swaps the blocks of a todo inside a posted message for `new`, an empty `new` removes them
+fn replace_todo_blocks(message: &Value, id: uuid::Uuid, mut new: Vec<Value>) -> Vec<Value> {
+    let id = id.to_string();
+    let mut blocks = Vec::new();
+
+    for block in message["blocks"].as_array().cloned().unwrap_or_default() {
+        if !block["block_id"]
+            .as_str()
+            .unwrap_or_default()
+            .ends_with(&id)
+        {
+            blocks.push(block);
+            continue;
+        }
+
+        blocks.append(&mut new);
+    }
+
+    blocks
+}
+
+/// Checks that the user who clicked owns `todo`, telling them otherwise
+async fn is_owner(payload: &SlackBlockActionsData, todo: &Todo, state: &ServerState) -> bool {
+    if todo.can_update(&payload.user.id) {
+        return true;
+    }
+
+    match &payload.response_url {
+        Some(response_url) => {
+            match state
+                .slack
+                .send_webhook(
+                    response_url.clone(),
+                    &mut json!({
+                        "text": format!("Only <@{}> can update this todo.", todo.slack_user),
+                        "replace_original": false
+                    }),
+                    true,
+                )
+                .await
+            {
+                Ok(_) => {}
+                Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+            }
+        }
+        None => println!(
+            "User {} tried to update a todo they don't own",
+            payload.user.id
+        ),
+    }
+
+    false
+}
+
+async fn todo_delete(
+    payload: &SlackBlockActionsData,
+    action: &SlackInteractionAction,
+    state: &ServerState,
+) {
+    let mut todo = match fetch_action_todo(action, state).await {
+        Some(v) => v,
+        None => return,
+    };
+
+    if !is_owner(payload, &todo, state).await {
+        return;
+    }
+
+    match todo.delete(&state.db).await {
+        Ok(_) => {}
+        Err(err) => {
+            println!("An error occured deleting todo from the database. {err}");
+            return;
+        }
+    }
+
+    let response_url = match &payload.response_url {
+        Some(v) => v.clone(),
+        None => return,
+    };
+
+    let blocks = replace_todo_blocks(&payload.message, todo.id, Vec::new());
+
+    match state
+        .slack
+        .send_webhook(
+            response_url,
+            &mut json!({"blocks": blocks, "replace_original": true}),
+            false,
+        )
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+    }
+}
+
+async fn todo_trash_action(
+    payload: &SlackBlockActionsData,
+    action: &SlackInteractionAction,
+    state: &ServerState,
+    purge: bool,
+) {
+    let mut todo = match fetch_action_todo(action, state).await {
+        Some(v) => v,
+        None => return,
     };
+
+    if !is_owner(payload, &todo, state).await {
+        return;
+    }
+
+    let res = if purge {
+        todo.purge(&state.db).await
+    } else {
+        todo.restore(&state.db).await
+    };
+
+    match res {
+        Ok(_) => {}
+        Err(err) => {
+            println!("An error occured updating todo in the database. {err}");
+            return;
+        }
+    }
+
+    let response_url = match &payload.response_url {
+        Some(v) => v.clone(),
+        None => {
+            println!("Block action did not contain a response url");
+            return;
+        }
+    };
+
+    // Trash listings are ephemeral, Slack doesn't send their blocks back so render them again
+    let mut data = match trash_message(&payload.user.id, state).await {
+        Some(v) => v,
+        None => return,
+    };
+    data["replace_original"] = Value::Bool(true);
+
+    match state
+        .slack
+        .send_webhook(response_url, &mut data, true)
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+    }
+}
+
+/// Completes the todo of a reminder DM, or snoozes it when `snooze` is given
+async fn reminder_action(
+    payload: &SlackBlockActionsData,
+    action: &SlackInteractionAction,
+    state: &ServerState,
+    snooze: Option<Duration>,
+) {
+    let mut todo = match fetch_action_todo(action, state).await {
+        Some(v) => v,
+        None => return,
+    };
+
+    if !is_owner(payload, &todo, state).await {
+        return;
+    }
+
+    let res = match snooze {
+        Some(snooze) => {
+            let due_at = Utc::now() + snooze;
+            todo.set_due(Some(due_at), &state.db).await.map(|_| {
+                format!(
+                    ":zzz: Snoozed *{}* until {}",
+                    todo.title,
+                    dates::slack_date(due_at)
+                )
+            })
+        }
+        None => complete_todo(&mut todo, state)
+            .await
+            .map(|next| match next {
+                Some(next) => format!(
+                    ":white_check_mark: Completed *{}*, the next one is due {}",
+                    todo.title,
+                    next.due_at.map(dates::slack_date).unwrap_or_default()
+                ),
+                None => format!(":white_check_mark: Completed *{}*", todo.title),
+            }),
+    };
+
+    let text = match res {
+        Ok(v) => v,
+        Err(err) => {
+            println!("An error occured updating todo in the database. {err}");
+            return;
+        }
+    };
+
+    let response_url = match &payload.response_url {
+        Some(v) => v.clone(),
+        None => {
+            println!("Block action did not contain a response url");
+            return;
+        }
+    };
+
+    match state
+        .slack
+        .send_webhook(
+            response_url,
+            &mut json!({"text": text, "replace_original": true}),
+            false,
+        )
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+    }
 }
 
 async fn create_modal(payload: &SlackInteractionData, state: &ServerState, action: &Action) {
@@ -188,13 +908,52 @@ async fn create_modal(payload: &SlackInteractionData, state: &ServerState, actio
         ["input-description-action"]["value"]
         .as_str();
 
+    let due_date = payload.view.state["values"]["input-due-date"]["input-due-date-action"]
+        ["selected_date"]
+        .as_str()
+        .and_then(|v| NaiveDate::parse_from_str(v, "%Y-%m-%d").ok());
+    let due_time = payload.view.state["values"]["input-due-time"]["input-due-time-action"]
+        ["selected_time"]
+        .as_str()
+        .and_then(|v| NaiveTime::parse_from_str(v, "%H:%M").ok());
+
+    let due_at = match (due_date, due_time) {
+        (None, None) => None,
+        (date, time) => {
+            let tz = dates::user_timezone(&state.slack, &action.slack_user).await;
+            let date = date.unwrap_or_else(|| Utc::now().with_timezone(&tz).date_naive());
+
+            Some(dates::resolve(
+                date,
+                time.unwrap_or_else(dates::end_of_day),
+                tz,
+            ))
+        }
+    };
+
+    let recurrence = modal_recurrence(&payload.view.state["values"], due_at);
+
     let mut todo = Todo {
         title: title
             .expect("Slack Interaction did not contain title! Is it in the modal?")
             .to_string(),
         description: description.map(str::to_string),
-        slack_user: action.slack_user.clone(),
-        ..Default::default()
+        slack_user: payload.view.state["values"]["input-assignee"]["input-assignee-action"]
+            ["selected_user"]
+            .as_str()
+            .unwrap_or(&action.slack_user)
+            .to_string(),
+        created_by: Some(action.slack_user.clone()),
+        source_permalink: Some(payload.view.private_metadata.clone()).filter(|v| !v.is_empty()),
+        due_at,
+        recurrence: recurrence.map(|v| v.to_string()),
+        tags: modal_tags(&payload.view.state["values"]),
+        priority: payload.view.state["values"]["input-priority"]["input-priority-action"]
+            ["selected_option"]["value"]
+            .as_str()
+            .and_then(|v| v.parse::<Priority>().ok())
+            .unwrap_or_default(),
+        ..Todo::new(&state.tenant)
     };
 
     match todo.assign_id().insert(&state.db).await {
@@ -205,11 +964,180 @@ async fn create_modal(payload: &SlackInteractionData, state: &ServerState, actio
         }
     }
 
+    match Tag::assign(todo.id, &todo.tags, &state.db).await {
+        Ok(_) => {}
+        Err(err) => println!("An error occured assigning tags to the todo. {err}"),
+    }
+
+    notify_assignee(state, &mut todo).await;
+
+    let channel = payload.view.state["values"]["input-channel"]["input-channel-action"]
+        ["selected_conversation"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+
+    let mut block = created_block(&todo);
+    block.trim();
+
+    // Without a channel confirm in a DM, and refresh the Home tab the modal may be opened from
+    if channel.is_empty() {
+        message::post(state, action.slack_user.clone(), todo.id, &mut block.into()).await;
+        home::publish(&action.slack_user, state).await;
+        return;
+    }
+
+    match state
+        .slack
+        .send_ephemeral(block.data, channel, action.slack_user.clone())
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while creating an ephemeral messsage {err}"),
+    }
+}
+
+/// Tags picked in the create modal, both from the picker and the free entry input
+fn modal_tags(values: &Value) -> Vec<String> {
+    let picked = values["input-tags"]["input-tags-action"]["selected_options"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|v| v["value"].as_str().map(str::to_string))
+        .collect::<Vec<String>>();
+
+    let entered = values["input-tags-new"]["input-tags-new-action"]["value"]
+        .as_str()
+        .unwrap_or_default()
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(str::to_string)
+        .collect::<Vec<String>>();
+
+    let mut tags = picked
+        .iter()
+        .chain(entered.iter())
+        .filter_map(|v| Tag::normalize(v))
+        .collect::<Vec<String>>();
+    tags.sort();
+    tags.dedup();
+
+    tags
+}
+
+/// Builds the recurrence picked in the create modal, defaulting missing parts from the due date
+fn modal_recurrence(values: &Value, due_at: Option<DateTime<Utc>>) -> Option<Recurrence> {
+    let due = due_at.unwrap_or_else(Utc::now);
+    let n = values["input-recurrence-n"]["input-recurrence-n-action"]["value"]
+        .as_str()
+        .and_then(|v| v.parse::<u32>().ok())
+        .filter(|v| *v > 0);
+
+    match values["input-recurrence"]["input-recurrence-action"]["selected_option"]["value"]
+        .as_str()?
+    {
+        "daily" => Some(Recurrence::Daily),
+        "weekly" => {
+            let mut days = values["input-recurrence-days"]["input-recurrence-days-action"]
+                ["selected_options"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|v| v["value"].as_str().and_then(recurrence::weekday))
+                .collect::<Vec<Weekday>>();
+
+            if days.is_empty() {
+                days.push(due.weekday());
+            }
+
+            Some(Recurrence::Weekly(days))
+        }
+        "monthly" => Some(Recurrence::Monthly(
+            n.filter(|v| *v <= 31).unwrap_or_else(|| due.day()),
+        )),
+        "interval" => match n.unwrap_or(1) {
+            1 => Some(Recurrence::Daily),
+            n => Some(Recurrence::EveryNDays(n)),
+        },
+        other => {
+            println!("Unknown recurrence option {other}");
+            None
+        }
+    }
+}
+
+/// Marks a todo completed and creates its next occurrence when it repeats
+async fn complete_todo(todo: &mut Todo, state: &ServerState) -> Result<Option<Todo>, sqlx::Error> {
+    todo.set_completed(true, &state.db).await?;
+
+    if todo.recurrence.is_none() {
+        return Ok(None);
+    }
+
+    let tz = dates::user_timezone(&state.slack, &todo.slack_user).await;
+    todo.insert_next(tz, &state.db).await
+}
+
+async fn edit_modal(payload: &SlackInteractionData, state: &ServerState, action: &Action) {
+    match action.delete(&state.db).await {
+        Ok(_) => {}
+        Err(err) => {
+            println!("An error occured deleting the action. {err}");
+            return;
+        }
+    }
+
+    let id = match uuid::Uuid::parse_str(&payload.view.private_metadata) {
+        Ok(v) => v,
+        Err(err) => {
+            println!("Edit modal did not contain a valid todo id! {err}");
+            return;
+        }
+    };
+
+    let mut todo = match Todo::fetch(id, &state.tenant, &state.db).await {
+        Ok(v) => v,
+        Err(err) => {
+            println!("Failed to fetch todo! {err}");
+            return;
+        }
+    };
+
+    let title =
+        &payload.view.state["values"]["input-title"]["input-title-action"]["value"].as_str();
+    let description = &payload.view.state["values"]["input-description"]
+        ["input-description-action"]["value"]
+        .as_str();
+
+    todo.title = match title {
+        Some(v) => v.to_string(),
+        None => {
+            println!("Slack Interaction did not contain title! Is it in the modal?");
+            return;
+        }
+    };
+    todo.description = description.map(str::to_string);
+
+    match todo.update(&state.db).await {
+        Ok(_) => {}
+        Err(err) => {
+            println!("An error occured updating todo in the database. {err}");
+            return;
+        }
+    }
+
+    // Opened from the Home tab, which shows the changes
+    if action.slack_channel.is_empty() {
+        home::publish(&action.slack_user, state).await;
+        return;
+    }
+
     let mut template: HashMap<&str, String> = HashMap::new();
-    template.insert("title", todo.title);
-    template.insert("desc", todo.description.unwrap_or_default());
+    template.insert("title", json_string(&todo.title));
+    template.insert("desc", json_string(&todo.description.unwrap_or_default()));
 
-    let mut block = SlackBlock::new("created".to_string());
+    let mut block = SlackBlock::new("updated".to_string());
     block.load().fill(template).trim();
 
     match state
@@ -225,3 +1153,83 @@ async fn create_modal(payload: &SlackInteractionData, state: &ServerState, actio
         Err(err) => println!("An error occured while creating an ephemeral messsage {err}"),
     }
 }
+
+mod command;
+mod events;
+mod home;
+mod list;
+mod oauth;
+mod preferences;
+mod search;
+mod shortcuts;
+mod snooze;
+mod verify;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_add_without_title_prefills_the_create_modal() {
+        let quick = QuickAdd::parse("!high #docs #release <@U123|carol> due:fri 10am").unwrap();
+        let now = "2024-05-08T10:00:00Z".parse().unwrap();
+
+        let mut template = quick_add_template(quick, now, Tz::UTC);
+        for key in ["desc", "source", "tag_select", "initial_conversation"] {
+            template.insert(key, String::new());
+        }
+
+        let mut modal = SlackModal::new("create".to_string(), String::new());
+        modal.load().fill(template);
+        let view: Value = serde_json::from_str(&modal.data).unwrap();
+        let element = |block: &str| {
+            view["blocks"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|v| v["block_id"] == block)
+                .unwrap()["element"]
+                .clone()
+        };
+
+        let priority = element("input-priority");
+        assert!(priority["options"]
+            .as_array()
+            .unwrap()
+            .contains(&priority["initial_option"]));
+        assert_eq!(priority["initial_option"]["value"], "high");
+        assert_eq!(element("input-tags-new")["initial_value"], "#docs #release");
+        assert_eq!(element("input-assignee")["initial_user"], "U123");
+        assert_eq!(element("input-due-date")["initial_date"], "2024-05-10");
+        assert_eq!(element("input-due-time")["initial_time"], "10:00");
+    }
+
+    #[test]
+    fn empty_quick_add_leaves_the_create_modal_blank() {
+        let mut template = quick_add_template(QuickAdd::default(), Utc::now(), Tz::UTC);
+        for key in [
+            "desc",
+            "source",
+            "tag_select",
+            "initial_conversation",
+            "user",
+        ] {
+            template.insert(key, String::new());
+        }
+        for key in ["initial_priority", "initial_date", "initial_time"] {
+            template.entry(key).or_default();
+        }
+
+        let mut modal = SlackModal::new("create".to_string(), String::new());
+        modal.load().fill(template);
+        let view: Value = serde_json::from_str(&modal.data).unwrap();
+
+        assert!(!modal.data.contains("{{"));
+        assert!(view["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|v| v["element"].get("initial_option").is_none()
+                && v["element"].get("initial_date").is_none()));
+    }
+}
@@ -0,0 +1,102 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::{installation::Installation, ServerState};
+
+#[derive(Debug, Deserialize)]
+pub struct OAuthCallback {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Sends the user to slack to install the app to their workspace
+pub async fn slack_install(State(state): State<ServerState>) -> Response {
+    let oauth = match &state.oauth {
+        Some(v) => v,
+        None => return (StatusCode::NOT_FOUND, "Installing isn't enabled").into_response(),
+    };
+
+    Redirect::to(&oauth.authorize_url(&oauth.state(Utc::now().timestamp()))).into_response()
+}
+
+/// Slack redirects here after an install, the code is exchanged for the workspace's bot token
+pub async fn slack_oauth_callback(
+    State(state): State<ServerState>,
+    Query(callback): Query<OAuthCallback>,
+) -> (StatusCode, String) {
+    let oauth = match &state.oauth {
+        Some(v) => v,
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                "Installing isn't enabled".to_string(),
+            )
+        }
+    };
+
+    if let Some(err) = callback.error {
+        return (StatusCode::BAD_REQUEST, format!("Install cancelled: {err}"));
+    }
+
+    if !callback
+        .state
+        .is_some_and(|v| oauth.verify_state(&v, Utc::now().timestamp()))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            "This install link expired, please start again".to_string(),
+        );
+    }
+
+    let code = match callback.code {
+        Some(v) => v,
+        None => return (StatusCode::BAD_REQUEST, "Missing install code".to_string()),
+    };
+
+    let res = match state.slack.oauth_access(oauth, &code).await {
+        Ok(v) => v.data,
+        Err(err) => {
+            println!("An error occured exchanging the install code. {err}");
+            return (
+                StatusCode::BAD_GATEWAY,
+                "Slack didn't accept the install, please try again".to_string(),
+            );
+        }
+    };
+
+    let installation = match Installation::from_oauth(&res) {
+        Some(v) => v,
+        None => {
+            println!("oauth.v2.access response did not contain a team and token: {res}");
+            return (
+                StatusCode::BAD_GATEWAY,
+                "Slack didn't send the install details".to_string(),
+            );
+        }
+    };
+
+    match installation.save(&state.db).await {
+        Ok(_) => {}
+        Err(err) => {
+            println!("An error occured saving the installation. {err}");
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Couldn't save the install, please try again".to_string(),
+            );
+        }
+    }
+
+    (
+        StatusCode::OK,
+        format!(
+            "Installed to {}! Try /todo in slack.",
+            installation.team_name.unwrap_or_default()
+        ),
+    )
+}
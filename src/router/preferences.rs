@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use chrono::NaiveTime;
+
+use crate::{
+    action::{Action, ActionType},
+    dates,
+    preferences::Preferences,
+    slack::{
+        modal::SlackModal,
+        payloads::{SlackBlockActionsData, SlackInteractionData},
+    },
+    ServerState,
+};
+
+/// The only checkbox of the modal, also used as its initial option when the digest is on
+const DIGEST_OPTION: &str = r#"{"text": {"type": "plain_text", "text": "Send me a daily digest", "emoji": true}, "value": "digest"}"#;
+
+/// Opens the preferences modal with the current preferences of the user
+pub async fn open_modal(payload: &SlackBlockActionsData, state: &ServerState) {
+    let prefs = match fetch_or_new(&payload.user.id, state).await {
+        Some(v) => v,
+        None => return,
+    };
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("digest_option", DIGEST_OPTION.to_string());
+    template.insert(
+        "initial_options",
+        if prefs.digest_enabled {
+            format!(r#""initial_options": [{DIGEST_OPTION}],"#)
+        } else {
+            String::new()
+        },
+    );
+    template.insert("time", prefs.digest_time.format("%H:%M").to_string());
+    template.insert("timezone", prefs.timezone.clone());
+
+    let mut modal = SlackModal::new("preferences".to_string(), payload.trigger_id.clone());
+    modal.load().fill(template);
+
+    match state.slack.open_modal(&modal).await {
+        Ok(v) => {
+            let id = match v.data["view"]["id"].as_str() {
+                Some(v) => v,
+                None => {
+                    println!("ID not found in payload");
+                    return;
+                }
+            };
+
+            let mut action = Action::new(
+                ActionType::PreferencesModal,
+                id.to_string(),
+                payload.user.id.clone(),
+                payload.channel.clone().unwrap_or_default().id,
+                &state.tenant,
+            );
+
+            match action.assign_id().insert(&state.db).await {
+                Ok(_) => {}
+                Err(err) => println!("Action to database insertion error! {err}"),
+            }
+        }
+        Err(err) => println!("An error occured while openning a modal. {err}"),
+    }
+}
+
+pub async fn preferences_modal(
+    payload: &SlackInteractionData,
+    state: &ServerState,
+    action: &Action,
+) {
+    match action.delete(&state.db).await {
+        Ok(_) => {}
+        Err(err) => {
+            println!("An error occured deleting the action. {err}");
+            return;
+        }
+    }
+
+    let mut prefs = match fetch_or_new(&action.slack_user, state).await {
+        Some(v) => v,
+        None => return,
+    };
+
+    prefs.digest_enabled = payload.view.state["values"]["input-digest-enabled"]
+        ["input-digest-enabled-action"]["selected_options"]
+        .as_array()
+        .is_some_and(|v| v.iter().any(|option| option["value"] == "digest"));
+
+    if let Some(time) = payload.view.state["values"]["input-digest-time"]
+        ["input-digest-time-action"]["selected_time"]
+        .as_str()
+        .and_then(|v| NaiveTime::parse_from_str(v, "%H:%M").ok())
+    {
+        prefs.digest_time = time;
+    }
+
+    match prefs.save(&state.db).await {
+        Ok(_) => {}
+        Err(err) => println!("An error occured saving preferences. {err}"),
+    }
+}
+
+/// Saved preferences of the user, or the defaults in their slack timezone
+async fn fetch_or_new(user: &str, state: &ServerState) -> Option<Preferences> {
+    match Preferences::fetch(user, &state.tenant, &state.db).await {
+        Ok(Some(v)) => Some(v),
+        Ok(None) => {
+            let tz = dates::user_timezone(&state.slack, user).await;
+            Some(Preferences::new(
+                user.to_string(),
+                tz.name().to_string(),
+                &state.tenant,
+            ))
+        }
+        Err(err) => {
+            println!("Failed to fetch preferences! {err}");
+            None
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use super::command::Command;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    message,
+    slack::{block::SlackBlock, escape::json_string},
+    tenant::Tenant,
+    todo::{Todo, TODO_SELECT},
+};
+
+const RESULTS: i64 = 10;
+
+const USAGE: &str = "Usage: `/todo/search <terms>`, e.g. `/todo/search release notes -draft`";
+
+/// Todo matching a search, with the matching terms highlighted in bold
+#[derive(Debug, sqlx::FromRow)]
+struct SearchResult {
+    #[sqlx(flatten)]
+    todo: Todo,
+    title_headline: String,
+    description_headline: String,
+}
+
+pub async fn todo_search(Command(state, payload): Command) {
+    if payload.text.trim().is_empty() {
+        match state
+            .slack
+            .send_webhook(payload.response_url, &mut json!({"text": USAGE}), true)
+            .await
+        {
+            Ok(_) => {}
+            Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+        }
+
+        return;
+    }
+
+    let query = sqlx::query_as::<sqlx::Postgres, SearchResult>(&format!(
+        r#"SELECT s.*,
+        ts_headline('english', s.title, q, 'StartSel=*, StopSel=*, HighlightAll=true') AS title_headline,
+        ts_headline('english', coalesce(s.description, ''), q, 'StartSel=*, StopSel=*, HighlightAll=true') AS description_headline
+        FROM ({TODO_SELECT} WHERE t.slack_user = $1 AND t.team_id = $4 AND t.enterprise_id = $5 AND t.deleted_at IS NULL) s, websearch_to_tsquery('english', $2) q
+        WHERE s.search @@ q ORDER BY ts_rank(s.search, q) DESC, s.id LIMIT $3"#
+    ))
+    .bind(&payload.user_id)
+    .bind(&payload.text)
+    .bind(RESULTS)
+    .bind(&state.tenant.team_id)
+    .bind(&state.tenant.enterprise_id)
+    .fetch_all(&state.db)
+    .await;
+
+    let results = match query {
+        Ok(v) => v,
+        Err(err) => {
+            println!("Failed to search todos! {err}");
+            return;
+        }
+    };
+
+    if results.is_empty() {
+        match state
+            .slack
+            .send_webhook(
+                payload.response_url,
+                &mut json!({"text": format!("No todos match `{}`", payload.text.trim())}),
+                true,
+            )
+            .await
+        {
+            Ok(_) => {}
+            Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+        }
+
+        return;
+    }
+
+    let count = results.len();
+    let todo_ids = results
+        .iter()
+        .map(|result| result.todo.id)
+        .collect::<Vec<_>>();
+    let list = results
+        .into_iter()
+        .map(|result| {
+            let mut todo = result.todo;
+            todo.headline = Some(result.title_headline);
+            if todo.description.is_some() {
+                todo.description = Some(result.description_headline);
+            }
+
+            todo.block().data
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("list", list);
+    template.insert("user", payload.user_name);
+    template.insert(
+        "showing",
+        format!(
+            "{count} best {} for `{}`",
+            if count == 1 { "match" } else { "matches" },
+            json_string(payload.text.trim())
+        ),
+    );
+    template.insert("pagination", String::new());
+
+    let mut block = SlackBlock::new("list".to_string());
+    block.load().fill(template).trim();
+
+    message::post_many(&state, payload.channel_id, &todo_ids, &mut block.into()).await;
+}
+
+/// Best matching open todo of `user` for `terms`, used to point at a todo from a command
+pub async fn find_todo(
+    user: &str,
+    terms: &str,
+    tenant: &Tenant,
+    db: &PgPool,
+) -> Result<Option<Todo>, sqlx::Error> {
+    sqlx::query_as::<sqlx::Postgres, Todo>(&format!(
+        r#"{TODO_SELECT}, websearch_to_tsquery('english', $2) q
+        WHERE t.slack_user = $1 AND t.team_id = $3 AND t.enterprise_id = $4
+        AND t.deleted_at IS NULL AND t.completed IS NOT TRUE AND t.search @@ q
+        ORDER BY ts_rank(t.search, q) DESC, t.due_at ASC NULLS LAST LIMIT 1"#
+    ))
+    .bind(user)
+    .bind(terms)
+    .bind(&tenant.team_id)
+    .bind(&tenant.enterprise_id)
+    .fetch_optional(db)
+    .await
+}
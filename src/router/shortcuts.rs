@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use crate::{
+    slack::{
+        escape::{json_string, unescape_html},
+        payloads::{SlackMessageActionData, SlackShortcutData},
+    },
+    ServerState,
+};
+
+use super::open_create_modal;
+
+/// Longest text slack accepts as the initial value of a text input
+const INPUT_LENGTH: usize = 3000;
+
+/// "Create todo from message" shortcut, prefills the create modal with the message and keeps
+/// a link back to it
+pub async fn message_action(payload: &SlackMessageActionData, state: &ServerState) {
+    let text = unescape_html(payload.message["text"].as_str().unwrap_or_default())
+        .chars()
+        .take(INPUT_LENGTH)
+        .collect::<String>();
+
+    let permalink = match state
+        .slack
+        .permalink(&payload.channel.id, &payload.message_ts)
+        .await
+    {
+        Ok(v) => v.data["permalink"].as_str().unwrap_or_default().to_string(),
+        Err(err) => {
+            println!("Couldn't fetch the permalink of the message. {err}");
+            String::new()
+        }
+    };
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("desc", json_string(&text));
+    template.insert("source", json_string(&permalink));
+
+    open_create_modal(
+        state,
+        payload.trigger_id.clone(),
+        payload.user.id.clone(),
+        payload.channel.id.clone(),
+        template,
+    )
+    .await;
+}
+
+/// Global "New todo" shortcut, there is no conversation so the modal asks where to confirm
+pub async fn global_shortcut(payload: &SlackShortcutData, state: &ServerState) {
+    open_create_modal(
+        state,
+        payload.trigger_id.clone(),
+        payload.user.id.clone(),
+        String::new(),
+        HashMap::new(),
+    )
+    .await;
+}
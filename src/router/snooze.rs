@@ -0,0 +1,63 @@
+use chrono::Utc;
+use serde_json::json;
+
+use crate::{
+    dates::{self, natural},
+    slack::payloads::SlackCommand,
+    ServerState,
+};
+
+use super::{command::Command, search::find_todo};
+
+pub const USAGE: &str = "Usage: `/todo/snooze <todo> until <when>`, e.g. `/todo/snooze release notes until next tue 10am`";
+
+/// Pushes back the due date of the open todo that best matches the text before `until`
+pub async fn todo_snooze(Command(state, payload): Command) {
+    let text = match snooze(&payload, &state).await {
+        Ok(v) => v,
+        Err(err) => format!(":warning: {err}\n{USAGE}"),
+    };
+
+    match state
+        .slack
+        .send_webhook(payload.response_url, &mut json!({"text": text}), true)
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+    }
+}
+
+async fn snooze(payload: &SlackCommand, state: &ServerState) -> Result<String, String> {
+    let (terms, when) = payload
+        .text
+        .rsplit_once(" until ")
+        .ok_or("Say until when the todo should be snoozed")?;
+
+    let tz = dates::user_timezone(&state.slack, &payload.user_id).await;
+    let due_at = natural::parse(when, Utc::now(), tz)
+        .ok_or(format!("`{}` is not a date I understand", when.trim()))?;
+
+    let mut todo = match find_todo(&payload.user_id, terms, &state.tenant, &state.db).await {
+        Ok(Some(v)) => v,
+        Ok(None) => return Err(format!("No open todo matches `{}`", terms.trim())),
+        Err(err) => {
+            println!("Failed to search todos! {err}");
+            return Err("Something went wrong looking for the todo".to_string());
+        }
+    };
+
+    match todo.set_due(Some(due_at), &state.db).await {
+        Ok(_) => {}
+        Err(err) => {
+            println!("An error occured updating todo in the database. {err}");
+            return Err("Something went wrong snoozing the todo".to_string());
+        }
+    }
+
+    Ok(format!(
+        ":zzz: Snoozed *{}* until {}",
+        todo.title,
+        dates::slack_date(due_at)
+    ))
+}
@@ -0,0 +1,53 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+
+use crate::{slack::signature, ServerState};
+
+/// Largest body read to check its signature, the same as axum's default limit
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Rejects requests that weren't signed by slack, before the extractors read the body
+pub async fn verify_slack(
+    State(state): State<ServerState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+
+    let body = match to_bytes(body, BODY_LIMIT).await {
+        Ok(v) => v,
+        Err(err) => {
+            println!("Couldn't read the request body: {err}");
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+
+    let res = signature::verify(
+        &state.signing_secret,
+        header(&parts.headers, "X-Slack-Request-Timestamp"),
+        header(&parts.headers, "X-Slack-Signature"),
+        &body,
+        Utc::now().timestamp(),
+    );
+
+    match res {
+        Ok(_) => next.run(Request::from_parts(parts, Body::from(body))).await,
+        Err(err) => {
+            println!("Rejected request to {}: {err}", parts.uri);
+            StatusCode::UNAUTHORIZED.into_response()
+        }
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+}
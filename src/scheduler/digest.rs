@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDate, NaiveTime, Utc};
+
+use crate::{
+    dates::{self, slack_date},
+    preferences::Preferences,
+    slack::{block::SlackBlock, escape::json_string},
+    todo::{Priority, Todo, TODO_SELECT},
+    ServerState,
+};
+
+/// Most todos listed per digest section, the rest are counted
+const SECTION_TODOS: usize = 10;
+
+/// DMs each user with open todos a digest of their day, at the time they picked
+pub async fn send(state: &ServerState) {
+    // Users get preferences with their timezone once they have open todos, so the digest
+    // time can be checked without asking slack every minute
+    match Preferences::fetch_missing_users(&state.db).await {
+        Ok(users) => {
+            for (user, tenant) in users {
+                let state = state
+                    .for_team(&tenant.team_id, Some(&tenant.enterprise_id))
+                    .await;
+                let tz = dates::user_timezone(&state.slack, &user).await;
+                match Preferences::new(user, tz.name().to_string(), &tenant)
+                    .save(&state.db)
+                    .await
+                {
+                    Ok(_) => {}
+                    Err(err) => println!("An error occured saving preferences. {err}"),
+                }
+            }
+        }
+        Err(err) => println!("Failed to fetch users without preferences! {err}"),
+    }
+
+    let due = match Preferences::fetch_due_digests(Utc::now(), &state.db).await {
+        Ok(v) => v,
+        Err(err) => {
+            println!("Failed to fetch due digests! {err}");
+            return;
+        }
+    };
+
+    for (prefs, today) in due {
+        let state = state
+            .for_team(&prefs.team_id, Some(&prefs.enterprise_id))
+            .await;
+        send_digest(&state, prefs, today).await;
+    }
+}
+
+/// Sends the digest of `today`, which is yesterday when a late evening digest goes out after
+/// midnight
+async fn send_digest(state: &ServerState, mut prefs: Preferences, today: NaiveDate) {
+    let tz = prefs.tz();
+
+    // Claim the digest before sending it, so a restart never sends it twice
+    match prefs.claim_digest(today, &state.db).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(err) => {
+            println!("An error occured recording a digest. {err}");
+            return;
+        }
+    }
+
+    let todos = sqlx::query_as::<sqlx::Postgres, Todo>(&format!(
+        r#"{TODO_SELECT} WHERE t.slack_user = $1 AND t.team_id = $2 AND t.enterprise_id = $3
+        AND t.completed IS NOT TRUE AND t.deleted_at IS NULL
+        ORDER BY t.due_at ASC NULLS LAST, t.priority DESC, t.title"#
+    ))
+    .bind(&prefs.slack_user)
+    .bind(&prefs.team_id)
+    .bind(&prefs.enterprise_id)
+    .fetch_all(&state.db)
+    .await;
+
+    let todos = match todos {
+        Ok(v) => v,
+        Err(err) => {
+            println!("Failed to fetch todos for the digest! {err}");
+            return;
+        }
+    };
+
+    if todos.is_empty() {
+        return;
+    }
+
+    let midnight = NaiveTime::MIN;
+    let completed: i64 = sqlx::query_scalar(
+        r#"SELECT COUNT(*) FROM todos WHERE slack_user = $1 AND team_id = $4 AND enterprise_id = $5
+        AND completed AND completed_at >= $2 AND completed_at < $3"#,
+    )
+    .bind(&prefs.slack_user)
+    .bind(dates::resolve(today - Duration::days(1), midnight, tz))
+    .bind(dates::resolve(today, midnight, tz))
+    .bind(&prefs.team_id)
+    .bind(&prefs.enterprise_id)
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or_else(|err| {
+        println!("Failed to count completed todos! {err}");
+        0
+    });
+
+    let mut overdue = Vec::new();
+    let mut due_today = Vec::new();
+    let mut important = Vec::new();
+
+    for todo in todos.iter() {
+        match todo.due_at {
+            Some(due) if todo.is_overdue() => {
+                overdue.push(format!("{}, overdue since {}", line(todo), slack_date(due)))
+            }
+            Some(due) if due.with_timezone(&tz).date_naive() == today => {
+                due_today.push(format!("{}, due {}", line(todo), slack_date(due)))
+            }
+            _ if matches!(todo.priority, Priority::High | Priority::Urgent) => {
+                important.push(line(todo))
+            }
+            _ => {}
+        }
+    }
+
+    let sections = [
+        (":warning: Overdue", overdue),
+        (":alarm_clock: Due today", due_today),
+        (":red_circle: High priority", important),
+    ]
+    .into_iter()
+    .filter(|(_, lines)| !lines.is_empty())
+    .map(|(heading, lines)| section(heading, lines))
+    .collect::<String>();
+
+    let mut summary = match todos.len() {
+        1 => "You have *1* open todo".to_string(),
+        n => format!("You have *{n}* open todos"),
+    };
+    summary.push_str(&match completed {
+        0 => " and completed none yesterday.".to_string(),
+        1 => " and completed *1* yesterday :tada:".to_string(),
+        n => format!(" and completed *{n}* yesterday :tada:"),
+    });
+    if sections.is_empty() {
+        summary.push_str("\\nNothing is overdue, due today or high priority :relieved:");
+    }
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("summary", summary);
+    template.insert("sections", sections);
+
+    let mut block = SlackBlock::new("digest".to_string());
+    block.load().fill(template).trim();
+
+    match state
+        .slack
+        .send_block(prefs.slack_user.clone(), &mut block.into())
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => println!("An error occured while sending a digest. {err}"),
+    }
+
+    // Follow the user around when they travel, the next digest uses their current timezone
+    let current = dates::user_timezone(&state.slack, &prefs.slack_user).await;
+    if current != tz {
+        prefs.timezone = current.name().to_string();
+        match prefs.save(&state.db).await {
+            Ok(_) => {}
+            Err(err) => println!("An error occured saving preferences. {err}"),
+        }
+    }
+}
+
+/// Digest line of a todo, JSON-escaped
+fn line(todo: &Todo) -> String {
+    format!("• *{}*{}", json_string(&todo.title), todo.priority.badge())
+}
+
+/// Heading and lines of one digest section, followed by a comma
+fn section(heading: &str, lines: Vec<String>) -> String {
+    let mut list = lines
+        .iter()
+        .take(SECTION_TODOS)
+        .cloned()
+        .collect::<Vec<String>>();
+    if lines.len() > SECTION_TODOS {
+        list.push(format!("_…and {} more_", lines.len() - SECTION_TODOS));
+    }
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("heading", heading.to_string());
+    template.insert("list", list.join("\\n"));
+
+    let mut block = SlackBlock::new("digest-section".to_string());
+    block.load().fill(template);
+
+    format!("{},", block.data)
+}
@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use crate::ServerState;
+
+pub mod digest;
+pub mod reminders;
+
+/// How often the background jobs look for work
+const TICK: Duration = Duration::from_secs(60);
+
+/// Runs the background jobs next to the axum server
+pub async fn run(state: ServerState) {
+    let mut interval = tokio::time::interval(TICK);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+
+        reminders::send(&state).await;
+        digest::send(&state).await;
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+
+use crate::{
+    dates::slack_date,
+    message,
+    reminder::{Reminder, ReminderKind},
+    slack::{block::SlackBlock, escape::json_string},
+    todo::Todo,
+    ServerState,
+};
+
+/// How long before the due date the upcoming reminder is sent
+const LEAD: Duration = Duration::minutes(30);
+
+/// DMs the owners of todos that are about to come due or are overdue
+pub async fn send(state: &ServerState) {
+    let now = Utc::now();
+
+    for (kind, from, until) in [
+        (ReminderKind::Overdue, None, now),
+        (ReminderKind::Upcoming, Some(now), now + LEAD),
+    ] {
+        let todos = match Todo::fetch_unreminded(kind, from, until, &state.db).await {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Failed to fetch todos to remind! {err}");
+                continue;
+            }
+        };
+
+        for todo in todos {
+            let state = state
+                .for_team(&todo.team_id, Some(&todo.enterprise_id))
+                .await;
+            remind(&state, todo, kind).await;
+        }
+    }
+}
+
+async fn remind(state: &ServerState, todo: Todo, kind: ReminderKind) {
+    let due_at = match todo.due_at {
+        Some(v) => v,
+        None => return,
+    };
+
+    // Claim the reminder before sending it, so a restart never sends it twice
+    match Reminder::new(todo.id, kind, due_at).claim(&state.db).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(err) => {
+            println!("An error occured recording a reminder. {err}");
+            return;
+        }
+    }
+
+    let mut template: HashMap<&str, String> = HashMap::new();
+    template.insert("id", todo.id.to_string());
+    template.insert("title", json_string(&todo.title));
+    template.insert(
+        "status",
+        match kind {
+            ReminderKind::Upcoming => format!(":alarm_clock: Due {}", slack_date(due_at)),
+            ReminderKind::Overdue => format!(":warning: Overdue since {}", slack_date(due_at)),
+        },
+    );
+
+    let mut block = SlackBlock::new("reminder".to_string());
+    block.load().fill(template).trim();
+
+    message::post(state, todo.slack_user, todo.id, &mut block.into()).await;
+}
@@ -42,8 +42,8 @@ impl SlackBlock {
     }
 }
 
-impl Into<Value> for SlackBlock {
-    fn into(self) -> Value {
-        serde_json::from_str(&self.data).unwrap_or_default()
+impl From<SlackBlock> for Value {
+    fn from(block: SlackBlock) -> Self {
+        serde_json::from_str(&block.data).unwrap_or_default()
     }
 }
@@ -1,19 +1,46 @@
+use serde_json::Value;
+
+#[derive(Debug, Clone)]
 pub struct SlackEscape {
     pub id: String,
     pub display: String,
 }
 
 impl SlackEscape {
-    pub fn from(s: String) -> Self {
-        let mut chars = s.chars();
-        chars.next();
-        chars.next();
-        chars.next_back();
-
-        let s: Vec<&str> = chars.as_str().split("|").collect();
-        Self {
-            id: s[0].to_string(),
-            display: s[1].to_string(),
+    /// Parses a user mention, both `<@U123|name>` and `<@U123>`, the display falls back to the id
+    pub fn user(s: &str) -> Option<Self> {
+        Self::parse(s, "<@")
+    }
+
+    /// Parses a channel mention, both `<#C123|name>` and `<#C123>`
+    pub fn channel(s: &str) -> Option<Self> {
+        Self::parse(s, "<#")
+    }
+
+    fn parse(s: &str, prefix: &str) -> Option<Self> {
+        let inner = s.strip_prefix(prefix)?.strip_suffix('>')?;
+        let (id, display) = inner.split_once('|').unwrap_or((inner, inner));
+
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
         }
+
+        Some(Self {
+            id: id.to_string(),
+            display: display.to_string(),
+        })
     }
 }
+
+/// Undoes the escaping slack applies to `&`, `<` and `>` in message and command text
+pub fn unescape_html(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Escapes user input so it can be filled into a JSON block or modal template
+pub fn json_string(s: &str) -> String {
+    let quoted = Value::String(s.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
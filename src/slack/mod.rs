@@ -1,36 +1,28 @@
-use std::env;
-
-use reqwest::{
-    header::{AUTHORIZATION, CONTENT_TYPE},
-    Response,
-};
+use reqwest::{header::CONTENT_TYPE, RequestBuilder};
 
 use serde_json::{json, Value};
 
-use self::modal::SlackModal;
+use self::{
+    modal::SlackModal,
+    oauth::SlackOAuth,
+    request::{Budgets, SlackError, SlackResponse},
+};
 
 #[derive(Debug, Default, Clone)]
 pub struct SlackApp {
     client: reqwest::Client,
+    token: String,
+    budgets: Budgets,
 }
 
 impl SlackApp {
-    pub fn new() -> Self {
+    /// Client for the workspace the bot `token` belongs to
+    pub fn new(token: String) -> Self {
         let mut headers = reqwest::header::HeaderMap::new();
         headers.insert(
             CONTENT_TYPE,
             "application/json; charset=utf-8".parse().unwrap(), // Unwrap kept; Hardcoded data
         );
-        headers.insert(
-            AUTHORIZATION,
-            format!(
-                "Bearer {}",
-                env::var("SLACK_TOKEN")
-                    .expect("Can't find SLACK_TOKEN environment variable, is it there?")
-            )
-            .parse()
-            .expect("Bearer token is not valid! Is the SLACK_TOKEN environment variable correct?"),
-        );
 
         Self {
             client: reqwest::Client::builder()
@@ -39,59 +31,93 @@ impl SlackApp {
                 // .proxy(reqwest::Proxy::https("http://localhost:8080").unwrap())
                 .build()
                 .expect("An error occured while building the reqwest client!"),
+            token,
+            budgets: Budgets::default(),
         }
     }
 
-    async fn validate_slack(&self, response: Response) -> Result<Value, Value> {
-        let data = response.json().await;
+    /// The same client for another workspace, the connection pool and budgets are shared
+    pub fn with_token(&self, token: String) -> Self {
+        Self {
+            client: self.client.clone(),
+            token,
+            budgets: self.budgets.clone(),
+        }
+    }
+
+    pub fn has_token(&self) -> bool {
+        !self.token.is_empty()
+    }
 
-        let data: Value = match data {
+    /// Sends a request to the web API `method` through the shared request path, and checks
+    /// that slack answered `ok`
+    async fn call(
+        &self,
+        method: &'static str,
+        request: RequestBuilder,
+    ) -> Result<SlackResponse, SlackError> {
+        let (response, retries) =
+            match request::send(&self.budgets, &self.token, method, request).await {
+                Ok(v) => v,
+                Err(err) => {
+                    println!("An error occured while sending request to slack API: {err}");
+                    return Err(err);
+                }
+            };
+
+        let data: Value = match response.json().await {
             Ok(v) => v,
             Err(err) => {
                 println!("An error occured while deserializing the slack data! {err}");
-                return Err(json!({"msg": "Deserialization error"}));
+                return Err(SlackError::Deserialize(err.to_string()));
             }
         };
 
-        if data["ok"].as_bool().unwrap_or(false) == false {
+        if !data["ok"].as_bool().unwrap_or(false) {
             println!("Slack API Error! {:?}", data);
-            return Err(data);
+            return Err(SlackError::Api(data));
         }
 
-        Ok(data)
+        let response = SlackResponse { data, retries };
+        if response.retried() {
+            println!("{method} went through after {retries} retries");
+        }
+
+        Ok(response)
     }
 
-    pub async fn send_message(&self, text: String, channel: String) -> Result<Value, Value> {
-        match self
-            .client
-            .post("https://slack.com/api/chat.postMessage")
-            .json(&json!({"text": text, "channel": channel}))
-            .send()
-            .await
-        {
-            Ok(v) => self.validate_slack(v).await,
-            Err(err) => {
-                println!("An error occured while sending request to slack API: {err}");
-                Err(json!({"msg": "Slack API Request Error"}))
-            }
-        }
+    pub async fn send_message(
+        &self,
+        text: String,
+        channel: String,
+    ) -> Result<SlackResponse, SlackError> {
+        self.call(
+            "chat.postMessage",
+            self.client
+                .post("https://slack.com/api/chat.postMessage")
+                .bearer_auth(&self.token)
+                .json(&json!({"text": text, "channel": channel})),
+        )
+        .await
     }
 
-    pub async fn send_block(&self, channel: String, block: &mut Value) {
+    pub async fn send_block(
+        &self,
+        channel: String,
+        block: &mut Value,
+    ) -> Result<SlackResponse, SlackError> {
         let mut data: Value = Value::default();
         data["blocks"] = block.clone();
         data["channel"] = Value::String(channel);
 
-        match self
-            .client
-            .post("https://slack.com/api/chat.postMessage")
-            .json(&data)
-            .send()
-            .await
-        {
-            Ok(_) => {}
-            Err(err) => println!("An error occured while sending a slack webhook: {err}"),
-        }
+        self.call(
+            "chat.postMessage",
+            self.client
+                .post("https://slack.com/api/chat.postMessage")
+                .bearer_auth(&self.token)
+                .json(&data),
+        )
+        .await
     }
 
     pub async fn send_ephemeral(
@@ -99,62 +125,166 @@ impl SlackApp {
         blocks: String,
         channel: String,
         user: String,
-    ) -> Result<Value, Value> {
+    ) -> Result<SlackResponse, SlackError> {
         let body = format!(
             r#"{{"blocks": {}, "channel": "{}", "user": "{}"}}"#,
             blocks, channel, user
         );
 
-        match self
-            .client
-            .post("https://slack.com/api/chat.postEphemeral")
-            .body(body)
-            .send()
-            .await
-        {
-            Ok(v) => self.validate_slack(v).await,
-            Err(err) => {
-                println!("An error occured while sending request to slack API: {err}");
-                Err(json!({"msg": "Slack API Request Error"}))
-            }
-        }
+        self.call(
+            "chat.postEphemeral",
+            self.client
+                .post("https://slack.com/api/chat.postEphemeral")
+                .bearer_auth(&self.token)
+                .body(body),
+        )
+        .await
     }
 
-    pub async fn send_webhook(&self, webhook: String, block: &mut Value, ephemeral: bool) {
+    /// Posts to a response url, which answers with plain text instead of JSON. Expired urls
+    /// answer 404, so only a success status counts
+    pub async fn send_webhook(
+        &self,
+        webhook: String,
+        block: &mut Value,
+        ephemeral: bool,
+    ) -> Result<SlackResponse, SlackError> {
         let data = block;
         if ephemeral {
             data["response_type"] = Value::String("ephemeral".to_string())
         }
 
-        match self.client.post(webhook).json(data).send().await {
-            Ok(_) => {}
-            Err(err) => println!("An error occured while sending a slack webhook: {err}"),
+        let request = self.client.post(webhook).json(data);
+        let (response, retries) =
+            request::send(&self.budgets, &self.token, "response_url", request).await?;
+
+        let status = response.status();
+        let body = match response.text().await {
+            Ok(v) => v,
+            Err(err) => return Err(SlackError::Deserialize(err.to_string())),
+        };
+
+        if !status.is_success() {
+            return Err(SlackError::Webhook { status, body });
         }
+
+        Ok(SlackResponse {
+            data: Value::String(body),
+            retries,
+        })
     }
 
-    pub async fn open_modal(&self, modal: &SlackModal) -> Result<Value, Value> {
+    pub async fn open_modal(&self, modal: &SlackModal) -> Result<SlackResponse, SlackError> {
         let data = format!(
             r#"{{"trigger_id": "{}", "view": {}}}"#,
             modal.trigger, modal.data
         );
 
-        match self
-            .client
-            .post("https://slack.com/api/views.open")
-            .body(data)
-            .send()
-            .await
-        {
-            Ok(v) => self.validate_slack(v).await,
-            Err(err) => {
-                println!("An error occured while sending request to slack API: {err}");
-                Err(json!({"msg": "Slack API Request Error"}))
-            }
+        self.call(
+            "views.open",
+            self.client
+                .post("https://slack.com/api/views.open")
+                .bearer_auth(&self.token)
+                .body(data),
+        )
+        .await
+    }
+
+    pub async fn publish_view(
+        &self,
+        user: &str,
+        view: &Value,
+    ) -> Result<SlackResponse, SlackError> {
+        self.call(
+            "views.publish",
+            self.client
+                .post("https://slack.com/api/views.publish")
+                .bearer_auth(&self.token)
+                .json(&json!({"user_id": user, "view": view})),
+        )
+        .await
+    }
+
+    pub async fn permalink(
+        &self,
+        channel: &str,
+        message_ts: &str,
+    ) -> Result<SlackResponse, SlackError> {
+        self.call(
+            "chat.getPermalink",
+            self.client
+                .get("https://slack.com/api/chat.getPermalink")
+                .bearer_auth(&self.token)
+                .query(&[("channel", channel), ("message_ts", message_ts)]),
+        )
+        .await
+    }
+
+    /// Exchanges the code of an install for the bot token, this call isn't authenticated
+    /// with a token but with the app credentials
+    pub async fn oauth_access(
+        &self,
+        oauth: &SlackOAuth,
+        code: &str,
+    ) -> Result<SlackResponse, SlackError> {
+        let mut form = vec![
+            ("client_id", oauth.client_id.as_str()),
+            ("client_secret", oauth.client_secret.as_str()),
+            ("code", code),
+        ];
+        if let Some(redirect_uri) = &oauth.redirect_uri {
+            form.push(("redirect_uri", redirect_uri));
         }
+
+        self.call(
+            "oauth.v2.access",
+            self.client
+                .post("https://slack.com/api/oauth.v2.access")
+                .form(&form),
+        )
+        .await
+    }
+
+    /// Attaches `body["unfurls"]` to the shared links of a message, `body` also names the
+    /// message with `channel` and `ts`, or `unfurl_id` and `source` in the composer
+    pub async fn unfurl(&self, body: &Value) -> Result<SlackResponse, SlackError> {
+        self.call(
+            "chat.unfurl",
+            self.client
+                .post("https://slack.com/api/chat.unfurl")
+                .bearer_auth(&self.token)
+                .json(body),
+        )
+        .await
+    }
+
+    pub async fn user_info(&self, user: &str) -> Result<SlackResponse, SlackError> {
+        self.call(
+            "users.info",
+            self.client
+                .get("https://slack.com/api/users.info")
+                .bearer_auth(&self.token)
+                .query(&[("user", user)]),
+        )
+        .await
+    }
+
+    /// Workspace and bot user the token belongs to
+    pub async fn auth_test(&self) -> Result<SlackResponse, SlackError> {
+        self.call(
+            "auth.test",
+            self.client
+                .post("https://slack.com/api/auth.test")
+                .bearer_auth(&self.token),
+        )
+        .await
     }
 }
 
 pub mod block;
 pub mod escape;
 pub mod modal;
+pub mod oauth;
 pub mod payloads;
+pub mod request;
+pub mod signature;
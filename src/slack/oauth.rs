@@ -0,0 +1,166 @@
+use std::env;
+
+use hmac::{Hmac, Mac};
+use reqwest::Url;
+use sha2::Sha256;
+
+/// Bot scopes asked for on install, covering the commands, shortcuts and events the app uses
+const SCOPES: &str =
+    "commands,chat:write,users:read,reactions:read,im:history,links:read,links:write,channels:read,groups:read";
+
+/// How long an install link stays valid
+const STATE_MAX_AGE: i64 = 60 * 10;
+
+/// Credentials of the slack app for the OAuth v2 install flow
+#[derive(Debug, Clone)]
+pub struct SlackOAuth {
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: Option<String>,
+}
+
+impl SlackOAuth {
+    /// Reads the app credentials, None when the install flow isn't configured
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            client_id: var("SLACK_CLIENT_ID")?,
+            client_secret: var("SLACK_CLIENT_SECRET")?,
+            redirect_uri: var("SLACK_REDIRECT_URI"),
+        })
+    }
+
+    /// Slack page that asks the user to install the app
+    pub fn authorize_url(&self, state: &str) -> String {
+        let mut params = vec![
+            ("client_id", self.client_id.as_str()),
+            ("scope", SCOPES),
+            ("state", state),
+        ];
+        if let Some(redirect_uri) = &self.redirect_uri {
+            params.push(("redirect_uri", redirect_uri));
+        }
+
+        Url::parse_with_params("https://slack.com/oauth/v2/authorize", params)
+            .map(String::from)
+            .unwrap_or_default()
+    }
+
+    /// Signed timestamp passed through the install, so callbacks can't be forged or replayed
+    /// later without keeping server side sessions
+    pub fn state(&self, now: i64) -> String {
+        format!("{now}.{}", hex::encode(self.sign(now)))
+    }
+
+    pub fn verify_state(&self, state: &str, now: i64) -> bool {
+        let (sent, signature) = match state.split_once('.') {
+            Some(v) => v,
+            None => return false,
+        };
+        let sent = match sent.parse::<i64>() {
+            Ok(v) => v,
+            Err(_) => return false,
+        };
+        if sent > now || now.abs_diff(sent) > STATE_MAX_AGE as u64 {
+            return false;
+        }
+
+        let signature = match hex::decode(signature) {
+            Ok(v) => v,
+            Err(_) => return false,
+        };
+
+        self.mac(sent).verify_slice(&signature).is_ok()
+    }
+
+    fn sign(&self, now: i64) -> Vec<u8> {
+        self.mac(now).finalize().into_bytes().to_vec()
+    }
+
+    fn mac(&self, now: i64) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.client_secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(format!("install:{now}").as_bytes());
+        mac
+    }
+}
+
+/// Environment variable, treating empty ones from the .env template as missing
+fn var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1531420618;
+
+    fn oauth(client_secret: &str) -> SlackOAuth {
+        SlackOAuth {
+            client_id: "1234.5678".to_string(),
+            client_secret: client_secret.to_string(),
+            redirect_uri: None,
+        }
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let oauth = oauth("8f742231b10e8888abcd99yyyzzz85a5");
+        let state = oauth.state(NOW);
+
+        assert!(oauth.verify_state(&state, NOW));
+        assert!(oauth.verify_state(&state, NOW + STATE_MAX_AGE));
+    }
+
+    #[test]
+    fn rejects_expired_states() {
+        let oauth = oauth("8f742231b10e8888abcd99yyyzzz85a5");
+
+        assert!(!oauth.verify_state(&oauth.state(NOW), NOW + STATE_MAX_AGE + 1));
+        assert!(!oauth.verify_state(&oauth.state(i64::MIN), NOW));
+    }
+
+    #[test]
+    fn rejects_states_from_the_future() {
+        let oauth = oauth("8f742231b10e8888abcd99yyyzzz85a5");
+
+        assert!(!oauth.verify_state(&oauth.state(NOW + 1), NOW));
+    }
+
+    #[test]
+    fn rejects_tampered_states() {
+        let oauth = oauth("8f742231b10e8888abcd99yyyzzz85a5");
+        let state = oauth.state(NOW);
+
+        // Signed by another app
+        assert!(!oauth.verify_state(&self::oauth("another secret").state(NOW), NOW));
+
+        // The timestamp moved without the signature
+        let (_, signature) = state.split_once('.').unwrap();
+        assert!(!oauth.verify_state(&format!("{}.{signature}", NOW - 1), NOW));
+
+        // A flipped signature byte
+        let mut flipped = state.clone().into_bytes();
+        let last = flipped.len() - 1;
+        flipped[last] = if flipped[last] == b'0' { b'1' } else { b'0' };
+        assert!(!oauth.verify_state(&String::from_utf8(flipped).unwrap(), NOW));
+    }
+
+    #[test]
+    fn rejects_malformed_states() {
+        let oauth = oauth("8f742231b10e8888abcd99yyyzzz85a5");
+        let state = oauth.state(NOW);
+        let (_, signature) = state.split_once('.').unwrap();
+
+        for state in [
+            String::new(),
+            NOW.to_string(),
+            format!("{NOW}."),
+            format!("{NOW}.not-hex"),
+            format!("yesterday.{signature}"),
+            format!(".{signature}"),
+        ] {
+            assert!(!oauth.verify_state(&state, NOW), "{state}");
+        }
+    }
+}
@@ -13,6 +13,8 @@ pub struct SlackCommand {
     pub response_url: String,
     pub trigger_id: String,
     pub api_app_id: String,
+    #[serde(default)]
+    pub enterprise_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -55,3 +57,181 @@ pub struct SlackInteractionData {
 pub struct SlackInteraction {
     pub payload: String,
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackInteractionAction {
+    pub r#type: String,
+    pub action_id: String,
+    pub block_id: String,
+    #[serde(default)]
+    pub value: Option<String>,
+    pub action_ts: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackInteractionChannel {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackBlockActionsData {
+    pub r#type: String,
+    pub user: SlackInteractionUser,
+    pub team: SlackInteractionTeam,
+    pub api_app_id: String,
+    pub trigger_id: String,
+    pub token: String,
+    #[serde(default)]
+    pub response_url: Option<String>,
+    #[serde(default)]
+    pub channel: Option<SlackInteractionChannel>,
+    #[serde(default)]
+    pub message: Value,
+    #[serde(default)]
+    pub container: Value,
+    pub actions: Vec<SlackInteractionAction>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackMessageActionData {
+    pub r#type: String,
+    pub callback_id: String,
+    pub user: SlackInteractionUser,
+    pub team: SlackInteractionTeam,
+    pub channel: SlackInteractionChannel,
+    pub trigger_id: String,
+    pub token: String,
+    pub response_url: String,
+    pub message_ts: String,
+    pub message: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackShortcutData {
+    pub r#type: String,
+    pub callback_id: String,
+    pub user: SlackInteractionUser,
+    pub team: SlackInteractionTeam,
+    pub trigger_id: String,
+    pub token: String,
+    pub action_ts: String,
+}
+
+/// Body of an Events API request
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SlackEventRequest {
+    UrlVerification {
+        challenge: String,
+    },
+    EventCallback(Box<SlackEventCallback>),
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SlackEventCallback {
+    pub team_id: String,
+    #[serde(default)]
+    pub enterprise_id: Option<String>,
+    pub api_app_id: String,
+    pub event_id: String,
+    pub event_time: i64,
+    #[serde(default)]
+    pub authorizations: Vec<SlackEventAuthorization>,
+    pub event: SlackEvent,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackEventAuthorization {
+    pub team_id: Option<String>,
+    pub user_id: String,
+    #[serde(default)]
+    pub is_bot: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SlackEvent {
+    AppHomeOpened(SlackAppHomeOpenedEvent),
+    ReactionAdded(SlackReactionEvent),
+    ReactionRemoved(SlackReactionEvent),
+    Message(SlackMessageEvent),
+    LinkShared(SlackLinkSharedEvent),
+    MemberJoinedChannel(SlackMemberJoinedChannelEvent),
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackAppHomeOpenedEvent {
+    pub user: String,
+    pub channel: String,
+    pub tab: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackReactionEvent {
+    pub user: String,
+    pub reaction: String,
+    pub item: SlackReactionItem,
+    #[serde(default)]
+    pub item_user: Option<String>,
+    pub event_ts: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackReactionItem {
+    pub r#type: String,
+    #[serde(default)]
+    pub channel: String,
+    #[serde(default)]
+    pub ts: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackMessageEvent {
+    #[serde(default)]
+    pub subtype: Option<String>,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub bot_id: Option<String>,
+    #[serde(default)]
+    pub text: String,
+    pub channel: String,
+    #[serde(default)]
+    pub channel_type: String,
+    pub ts: String,
+}
+
+/// Links to a domain of the app, shared in a message or in the message composer
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackLinkSharedEvent {
+    pub channel: String,
+    pub user: String,
+    pub message_ts: String,
+    pub links: Vec<SlackSharedLink>,
+    /// Set instead of a real channel and ts while the message is still being written
+    #[serde(default)]
+    pub unfurl_id: Option<String>,
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackSharedLink {
+    pub domain: String,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlackMemberJoinedChannelEvent {
+    pub user: String,
+    pub channel: String,
+    #[serde(default)]
+    pub channel_type: String,
+    #[serde(default)]
+    pub inviter: Option<String>,
+}
@@ -0,0 +1,464 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::{header::RETRY_AFTER, RequestBuilder, Response, StatusCode};
+use serde_json::Value;
+use tokio::time::Instant;
+
+/// Attempts after the first one before a transiently failing call is dropped
+const MAX_RETRIES: u32 = 3;
+
+/// Backoff before the first retry, doubled for each one after it
+const BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest a call waits for its budget or a `Retry-After`, past that it's dropped instead of
+/// holding up the request or job that made it
+const MAX_WAIT: Duration = Duration::from_secs(10);
+
+/// Longest a call bound to a `trigger_id` waits, the trigger expires 3 seconds after the click
+const TRIGGER_MAX_WAIT: Duration = Duration::from_millis(500);
+
+/// Window the tier budgets are counted over
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Rate limit tiers of the slack web API, see https://api.slack.com/apis/rate-limits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tier {
+    /// 50+ calls per minute
+    Tier3,
+    /// 100+ calls per minute
+    Tier4,
+    /// Methods with their own limits, e.g. posting is about one message per second
+    Special,
+    /// Not counted, response urls and the OAuth exchange
+    Unlimited,
+}
+
+impl Tier {
+    pub fn of(method: &str) -> Self {
+        match method {
+            "chat.postMessage" | "chat.postEphemeral" | "chat.getPermalink" | "auth.test" => {
+                Self::Special
+            }
+            "chat.unfurl" => Self::Tier3,
+            "views.open" | "views.publish" | "users.info" => Self::Tier4,
+            _ => Self::Unlimited,
+        }
+    }
+
+    /// Calls per minute per workspace
+    fn budget(&self) -> Option<usize> {
+        match self {
+            Self::Tier3 => Some(50),
+            Self::Tier4 => Some(100),
+            Self::Special => Some(60),
+            Self::Unlimited => None,
+        }
+    }
+}
+
+/// Whether a call to `method` can be repeated after slack may have acted on it. Posting twice
+/// shows the message twice and OAuth codes only work once, so those are only retried when slack
+/// certainly didn't get them
+fn idempotent(method: &str) -> bool {
+    !matches!(
+        method,
+        "chat.postMessage" | "chat.postEphemeral" | "response_url" | "oauth.v2.access"
+    )
+}
+
+/// Whether `method` needs a `trigger_id`, which expires too soon to wait or retry for long
+fn trigger_bound(method: &str) -> bool {
+    matches!(method, "views.open")
+}
+
+/// Longest a call to `method` may wait for its budget or a `Retry-After`
+fn max_wait(method: &str) -> Duration {
+    if trigger_bound(method) {
+        TRIGGER_MAX_WAIT
+    } else {
+        MAX_WAIT
+    }
+}
+
+/// Answer to a call slack accepted
+#[derive(Debug)]
+pub struct SlackResponse {
+    pub data: Value,
+    /// Failed attempts before this answer, 0 when the first one went through
+    pub retries: u32,
+}
+
+impl SlackResponse {
+    pub fn retried(&self) -> bool {
+        self.retries > 0
+    }
+}
+
+/// Why a call was given up on without an answer
+#[derive(Debug, Clone, PartialEq)]
+pub enum DropReason {
+    /// The budget of the method tier ran out for longer than the call may wait
+    Budget,
+    /// Slack kept answering 429, or asked to wait longer than the call may
+    RateLimited,
+    /// Slack kept failing with this server error
+    Unavailable(StatusCode),
+    /// The request kept failing before it got an answer
+    Network(String),
+}
+
+#[derive(Debug)]
+pub enum SlackError {
+    /// Slack answered but not `ok`, retrying won't change its mind
+    Api(Value),
+    /// The answer wasn't JSON
+    Deserialize(String),
+    /// A response url answered with an error status, e.g. 404 once it expired
+    Webhook { status: StatusCode, body: String },
+    /// No answer after `retries` retries
+    Dropped { reason: DropReason, retries: u32 },
+}
+
+impl fmt::Display for SlackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Api(data) => write!(f, "{data}"),
+            Self::Deserialize(err) => write!(f, "Deserialization error: {err}"),
+            Self::Webhook { status, body } => write!(f, "Webhook answered {status}: {body}"),
+            Self::Dropped { reason, retries } => {
+                write!(f, "Dropped after {retries} retries: ")?;
+                match reason {
+                    DropReason::Budget => write!(f, "rate limit budget exhausted"),
+                    DropReason::RateLimited => write!(f, "rate limited by slack"),
+                    DropReason::Unavailable(status) => write!(f, "slack answered {status}"),
+                    DropReason::Network(err) => write!(f, "{err}"),
+                }
+            }
+        }
+    }
+}
+
+/// Calls made per workspace token and method in the last minute, and until when slack asked
+/// to hold off. Shared by every clone of the client
+#[derive(Debug, Default, Clone)]
+pub struct Budgets(Arc<Mutex<HashMap<(String, &'static str), Budget>>>);
+
+#[derive(Debug, Default)]
+struct Budget {
+    sent: VecDeque<Instant>,
+    blocked_until: Option<Instant>,
+}
+
+impl Budgets {
+    /// Takes a slot in the budget of `method`, waiting for one when needed
+    async fn acquire(&self, token: &str, method: &'static str) -> Result<(), DropReason> {
+        let limit = match Tier::of(method).budget() {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        loop {
+            let wait = {
+                let mut budgets = self.0.lock().unwrap(); // Unwrap kept; Never held across a panic
+                let budget = budgets.entry((token.to_string(), method)).or_default();
+                let now = Instant::now();
+
+                while budget.sent.front().is_some_and(|v| now - *v >= WINDOW) {
+                    budget.sent.pop_front();
+                }
+
+                match budget.blocked_until.filter(|v| *v > now) {
+                    Some(until) => until - now,
+                    None if budget.sent.len() < limit => {
+                        budget.sent.push_back(now);
+                        return Ok(());
+                    }
+                    None => budget.sent[0] + WINDOW - now,
+                }
+            };
+
+            if wait > max_wait(method) {
+                return Err(DropReason::Budget);
+            }
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Holds every call of `method` back for `wait`, after a 429
+    fn block(&self, token: &str, method: &'static str, wait: Duration) {
+        let mut budgets = self.0.lock().unwrap(); // Unwrap kept; Never held across a panic
+        budgets
+            .entry((token.to_string(), method))
+            .or_default()
+            .blocked_until = Some(Instant::now() + wait);
+    }
+}
+
+/// Sends `request` to the slack `method` within its tier budget, returns the response with the
+/// number of retries it took. 429s are retried after their `Retry-After`, server and network
+/// errors with a jittered exponential backoff. Calls that aren't idempotent are only retried
+/// when they never reached slack: on 429s and failed connections. Calls bound to a
+/// `trigger_id` are never retried
+pub async fn send(
+    budgets: &Budgets,
+    token: &str,
+    method: &'static str,
+    request: RequestBuilder,
+) -> Result<(Response, u32), SlackError> {
+    let mut retries = 0;
+
+    loop {
+        let dropped = |reason| SlackError::Dropped { reason, retries };
+
+        if let Err(reason) = budgets.acquire(token, method).await {
+            return Err(dropped(reason));
+        }
+
+        // Bodies are never streamed, so the request can always be cloned
+        let attempt = request
+            .try_clone()
+            .expect("Slack requests must have a clonable body");
+
+        let (reason, wait) = match attempt.send().await {
+            Ok(res) if res.status() == StatusCode::TOO_MANY_REQUESTS => {
+                let wait = res
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse().ok())
+                    .map(Duration::from_secs)
+                    .unwrap_or(Duration::from_secs(1));
+
+                budgets.block(token, method, wait);
+
+                if wait > max_wait(method) {
+                    return Err(dropped(DropReason::RateLimited));
+                }
+
+                (DropReason::RateLimited, wait)
+            }
+            Ok(res) if res.status().is_server_error() => {
+                let reason = DropReason::Unavailable(res.status());
+                if !idempotent(method) {
+                    return Err(dropped(reason));
+                }
+
+                (reason, backoff(retries))
+            }
+            Ok(res) => return Ok((res, retries)),
+            Err(err) => {
+                let reason = DropReason::Network(err.to_string());
+                if !err.is_connect() && !idempotent(method) {
+                    return Err(dropped(reason));
+                }
+
+                (reason, backoff(retries))
+            }
+        };
+
+        // Retrying a trigger bound call would only fail later with an expired trigger
+        if retries == MAX_RETRIES || trigger_bound(method) {
+            return Err(dropped(reason));
+        }
+
+        println!("Retrying {method} in {}ms. {reason:?}", wait.as_millis());
+        tokio::time::sleep(wait).await;
+        retries += 1;
+    }
+}
+
+/// Full jitter, a random wait up to the exponential backoff of the attempt
+fn backoff(retries: u32) -> Duration {
+    let max = BACKOFF * 2u32.pow(retries);
+    Duration::from_millis(fastrand::u64(0..=max.as_millis() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use axum::{http::HeaderMap, Router};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Serves `status` with `headers` to every request, returns its url and a count of the
+    /// requests it got
+    async fn serve(
+        status: StatusCode,
+        headers: &'static [(&'static str, &'static str)],
+    ) -> (String, Arc<AtomicU32>) {
+        let hits = Arc::new(AtomicU32::new(0));
+        let counter = hits.clone();
+        let app = Router::new().fallback(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let mut map = HeaderMap::new();
+            for (name, value) in headers {
+                map.insert(*name, value.parse().unwrap());
+            }
+            async move { (status, map) }
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        (url, hits)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn acquire_waits_for_the_budget_window() {
+        let budgets = Budgets::default();
+        for _ in 0..60 {
+            assert_eq!(budgets.acquire("xoxb", "chat.postMessage").await, Ok(()));
+        }
+
+        // The oldest slot frees up in a minute, longer than a call may wait
+        assert_eq!(
+            budgets.acquire("xoxb", "chat.postMessage").await,
+            Err(DropReason::Budget)
+        );
+
+        // Other methods and workspaces have their own budgets
+        assert_eq!(budgets.acquire("xoxb", "chat.getPermalink").await, Ok(()));
+        assert_eq!(budgets.acquire("xoxp", "chat.postMessage").await, Ok(()));
+
+        tokio::time::advance(Duration::from_secs(55)).await;
+        let start = Instant::now();
+        assert_eq!(budgets.acquire("xoxb", "chat.postMessage").await, Ok(()));
+        assert_eq!(start.elapsed(), Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn long_retry_after_drops_the_call() {
+        let (url, hits) = serve(StatusCode::TOO_MANY_REQUESTS, &[("retry-after", "30")]).await;
+        let budgets = Budgets::default();
+
+        let res = send(
+            &budgets,
+            "xoxb",
+            "views.publish",
+            reqwest::Client::new().post(url),
+        )
+        .await;
+        assert!(matches!(
+            res,
+            Err(SlackError::Dropped {
+                reason: DropReason::RateLimited,
+                retries: 0
+            })
+        ));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // Slack asked to hold off, so the next call doesn't even go out
+        assert_eq!(
+            budgets.acquire("xoxb", "views.publish").await,
+            Err(DropReason::Budget)
+        );
+    }
+
+    #[tokio::test]
+    async fn server_errors_only_retry_idempotent_methods() {
+        let (url, hits) = serve(StatusCode::INTERNAL_SERVER_ERROR, &[]).await;
+        let client = reqwest::Client::new();
+
+        for method in ["chat.postMessage", "response_url"] {
+            hits.store(0, Ordering::SeqCst);
+            let res = send(&Budgets::default(), "xoxb", method, client.post(&url)).await;
+            assert!(matches!(
+                res,
+                Err(SlackError::Dropped {
+                    reason: DropReason::Unavailable(StatusCode::INTERNAL_SERVER_ERROR),
+                    retries: 0
+                })
+            ));
+            assert_eq!(hits.load(Ordering::SeqCst), 1, "{method}");
+        }
+
+        hits.store(0, Ordering::SeqCst);
+        let res = send(&Budgets::default(), "xoxb", "users.info", client.get(&url)).await;
+        assert!(matches!(
+            res,
+            Err(SlackError::Dropped {
+                retries: MAX_RETRIES,
+                ..
+            })
+        ));
+        assert_eq!(hits.load(Ordering::SeqCst), MAX_RETRIES + 1);
+    }
+
+    #[tokio::test]
+    async fn expired_response_urls_fail() {
+        let (url, _) = serve(StatusCode::NOT_FOUND, &[]).await;
+
+        let res = crate::slack::SlackApp::new(String::new())
+            .send_webhook(url, &mut serde_json::json!({"text": "Done"}), true)
+            .await;
+        assert!(matches!(
+            res,
+            Err(SlackError::Webhook {
+                status: StatusCode::NOT_FOUND,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn trigger_bound_calls_are_not_retried() {
+        let (url, hits) = serve(StatusCode::INTERNAL_SERVER_ERROR, &[]).await;
+
+        let res = send(
+            &Budgets::default(),
+            "xoxb",
+            "views.open",
+            reqwest::Client::new().post(url),
+        )
+        .await;
+        assert!(matches!(
+            res,
+            Err(SlackError::Dropped {
+                reason: DropReason::Unavailable(StatusCode::INTERNAL_SERVER_ERROR),
+                retries: 0
+            })
+        ));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn trigger_bound_calls_dont_wait_out_a_retry_after() {
+        let (url, hits) = serve(StatusCode::TOO_MANY_REQUESTS, &[("retry-after", "1")]).await;
+
+        let start = Instant::now();
+        let res = send(
+            &Budgets::default(),
+            "xoxb",
+            "views.open",
+            reqwest::Client::new().post(url),
+        )
+        .await;
+        assert!(matches!(
+            res,
+            Err(SlackError::Dropped {
+                reason: DropReason::RateLimited,
+                retries: 0
+            })
+        ));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert!(start.elapsed() < TRIGGER_MAX_WAIT);
+    }
+
+    #[test]
+    fn backoff_stays_within_the_exponential_bound() {
+        for retries in 0..=MAX_RETRIES {
+            for _ in 0..1000 {
+                assert!(backoff(retries) <= BACKOFF * 2u32.pow(retries));
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Requests signed longer ago than this are treated as replays
+const MAX_AGE: u64 = 60 * 5;
+
+/// Checks a request against its `X-Slack-Signature` and `X-Slack-Request-Timestamp` headers,
+/// see https://api.slack.com/authentication/verifying-requests-from-slack
+pub fn verify(
+    secret: &str,
+    timestamp: &str,
+    signature: &str,
+    body: &[u8],
+    now: i64,
+) -> Result<(), &'static str> {
+    // Anyone can compute the signature of an empty secret
+    if secret.is_empty() {
+        return Err("Signing secret isn't set");
+    }
+
+    let sent = timestamp
+        .parse::<i64>()
+        .map_err(|_| "Request timestamp isn't a number")?;
+    if now.abs_diff(sent) > MAX_AGE {
+        return Err("Request timestamp is too old");
+    }
+
+    let signature = signature
+        .strip_prefix("v0=")
+        .and_then(|v| hex::decode(v).ok())
+        .ok_or("Request signature isn't a v0 signature")?;
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|_| "Invalid signing secret")?;
+    mac.update(format!("v0:{timestamp}:").as_bytes());
+    mac.update(body);
+
+    // Compares in constant time
+    mac.verify_slice(&signature)
+        .map_err(|_| "Request signature doesn't match")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example from https://api.slack.com/authentication/verifying-requests-from-slack
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+    const TIMESTAMP: &str = "1531420618";
+    const NOW: i64 = 1531420618;
+    const SIGNATURE: &str = "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+    const BODY: &str = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+
+    #[test]
+    fn accepts_slacks_example() {
+        assert_eq!(
+            verify(SECRET, TIMESTAMP, SIGNATURE, BODY.as_bytes(), NOW),
+            Ok(())
+        );
+        assert_eq!(
+            verify(
+                SECRET,
+                TIMESTAMP,
+                SIGNATURE,
+                BODY.as_bytes(),
+                NOW + MAX_AGE as i64
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_empty_secrets() {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"").unwrap();
+        mac.update(format!("v0:{TIMESTAMP}:{BODY}").as_bytes());
+        let forged = format!("v0={}", hex::encode(mac.finalize().into_bytes()));
+
+        assert_eq!(
+            verify("", TIMESTAMP, &forged, BODY.as_bytes(), NOW),
+            Err("Signing secret isn't set")
+        );
+    }
+
+    #[test]
+    fn rejects_stale_timestamps() {
+        for now in [NOW + MAX_AGE as i64 + 1, NOW - MAX_AGE as i64 - 1] {
+            assert_eq!(
+                verify(SECRET, TIMESTAMP, SIGNATURE, BODY.as_bytes(), now),
+                Err("Request timestamp is too old")
+            );
+        }
+
+        for timestamp in [i64::MIN, i64::MAX] {
+            assert_eq!(
+                verify(
+                    SECRET,
+                    &timestamp.to_string(),
+                    SIGNATURE,
+                    BODY.as_bytes(),
+                    NOW
+                ),
+                Err("Request timestamp is too old")
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_signatures_without_prefix() {
+        let signature = SIGNATURE.trim_start_matches("v0=");
+
+        assert_eq!(
+            verify(SECRET, TIMESTAMP, signature, BODY.as_bytes(), NOW),
+            Err("Request signature isn't a v0 signature")
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_bodies() {
+        let body = BODY.replace("user_name=roadrunner", "user_name=coyote");
+
+        assert_eq!(
+            verify(SECRET, TIMESTAMP, SIGNATURE, body.as_bytes(), NOW),
+            Err("Request signature doesn't match")
+        );
+        assert_eq!(
+            verify(SECRET, "1531420619", SIGNATURE, BODY.as_bytes(), NOW),
+            Err("Request signature doesn't match")
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::tenant::Tenant;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Tag {
+    pub id: Uuid,
+    pub name: String,
+}
+
+impl Tag {
+    /// Lowercases a tag and strips the leading `#`, returns None when nothing valid is left
+    pub fn normalize(s: &str) -> Option<String> {
+        let name = s
+            .trim()
+            .trim_start_matches('#')
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == '.')
+            .take(64)
+            .collect::<String>();
+
+        if name.is_empty() {
+            None
+        } else {
+            Some(name)
+        }
+    }
+
+    /// Tags used on any of the user's todos, for the tag picker
+    pub async fn fetch_user(
+        user: &str,
+        tenant: &Tenant,
+        db: &PgPool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<sqlx::Postgres, Tag>(
+            r#"SELECT DISTINCT g.* FROM tags g JOIN todo_tags tt ON tt.tag_id = g.id JOIN todos t ON t.id = tt.todo_id
+            WHERE t.slack_user = $1 AND t.team_id = $2 AND t.enterprise_id = $3 ORDER BY g.name LIMIT 100"#,
+        )
+        .bind(user)
+        .bind(&tenant.team_id)
+        .bind(&tenant.enterprise_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Replaces the tags of a todo, creating the ones that don't exist yet
+    pub async fn assign(todo: Uuid, names: &[String], db: &PgPool) -> Result<(), sqlx::Error> {
+        let mut tx = db.begin().await?;
+
+        sqlx::query(r#"DELETE FROM todo_tags WHERE todo_id = $1"#)
+            .bind(todo)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"INSERT INTO tags (name) SELECT UNNEST($1::varchar[]) ON CONFLICT (name) DO NOTHING"#,
+        )
+        .bind(names)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"INSERT INTO todo_tags (todo_id, tag_id) SELECT $1, id FROM tags WHERE name = ANY($2) ON CONFLICT DO NOTHING"#,
+        )
+        .bind(todo)
+        .bind(names)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await
+    }
+}
@@ -0,0 +1,39 @@
+use sqlx::PgPool;
+
+/// Workspace a request comes from. Slack user ids are only unique within a workspace, so todos,
+/// actions and preferences are scoped by it. Both ids are empty when unknown
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tenant {
+    pub team_id: String,
+    pub enterprise_id: String,
+}
+
+impl Tenant {
+    pub fn new(team_id: &str, enterprise_id: Option<&str>) -> Self {
+        Self {
+            team_id: team_id.to_string(),
+            enterprise_id: enterprise_id.unwrap_or_default().to_string(),
+        }
+    }
+
+    /// Moves rows stored before workspaces were tracked into this workspace, returns how many
+    pub async fn claim_unscoped(&self, db: &PgPool) -> Result<u64, sqlx::Error> {
+        let mut tx = db.begin().await?;
+        let mut claimed = 0;
+
+        for table in ["todos", "actions", "preferences"] {
+            claimed += sqlx::query(&format!(
+                r#"UPDATE {table} SET team_id = $1, enterprise_id = $2 WHERE team_id = '' AND enterprise_id = ''"#
+            ))
+            .bind(&self.team_id)
+            .bind(&self.enterprise_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+
+        tx.commit().await?;
+
+        Ok(claimed)
+    }
+}
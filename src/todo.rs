@@ -1,9 +1,62 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr};
 
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use sqlx::{postgres::PgQueryResult, PgPool};
 
-use crate::slack::block::SlackBlock;
+use crate::{
+    dates::{self, slack_date},
+    recurrence::Recurrence,
+    reminder::ReminderKind,
+    slack::{block::SlackBlock, escape::json_string},
+    tag::Tag,
+    tenant::Tenant,
+};
+
+/// Declared from lowest to highest, so postgres sorts `todo_priority` by importance
+#[derive(Debug, Clone, Default, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "todo_priority", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    #[default]
+    None = 0,
+    Low = 1,
+    Medium = 2,
+    High = 3,
+    Urgent = 4,
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Priority::None),
+            "low" => Ok(Priority::Low),
+            "medium" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            "urgent" => Ok(Priority::Urgent),
+            _ => Err(format!("Invalid priority `{s}`")),
+        }
+    }
+}
+
+impl Priority {
+    pub fn badge(&self) -> &'static str {
+        match self {
+            Priority::None => "",
+            Priority::Low => " :large_blue_circle: `Low`",
+            Priority::Medium => " :large_yellow_circle: `Medium`",
+            Priority::High => " :red_circle: `High`",
+            Priority::Urgent => " :rotating_light: `Urgent`",
+        }
+    }
+}
+
+/// Selects todos as `t` together with their tag names
+pub const TODO_SELECT: &str = r#"SELECT t.*, ARRAY(SELECT g.name FROM todo_tags tt JOIN tags g ON g.id = tt.tag_id WHERE tt.todo_id = t.id ORDER BY g.name) AS tags FROM todos t"#;
 
 #[derive(Debug, Serialize, Deserialize, Default, sqlx::FromRow)]
 pub struct Todo {
@@ -11,7 +64,23 @@ pub struct Todo {
     pub title: String,
     pub description: Option<String>,
     pub completed: bool,
+    pub completed_at: Option<DateTime<Utc>>,
     pub slack_user: String,
+    pub team_id: String,
+    pub enterprise_id: String,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub recurrence: Option<String>,
+    pub recurs_from: Option<uuid::Uuid>,
+    pub priority: Priority,
+    pub created_by: Option<String>,
+    pub source_permalink: Option<String>,
+    #[sqlx(default)]
+    pub tags: Vec<String>,
+    /// Title with search matches in bold, shown instead of the bold title
+    #[sqlx(skip)]
+    #[serde(skip)]
+    pub headline: Option<String>,
 }
 
 impl Todo {
@@ -22,24 +91,480 @@ impl Todo {
     }
 
     pub async fn insert(&self, db: &PgPool) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query!(r#"INSERT INTO todos (id, title, description, completed, slack_user) VALUES ($1, $2, $3, $4, $5)"#,
-            self.id, self.title, self.description, self.completed, self.slack_user).execute(db).await
+        sqlx::query!(r#"INSERT INTO todos (id, title, description, completed, slack_user, due_at, recurrence, recurs_from, priority, created_by, source_permalink, team_id, enterprise_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)"#,
+            self.id, self.title, self.description, self.completed, self.slack_user, self.due_at, self.recurrence, self.recurs_from, self.priority as Priority, self.created_by, self.source_permalink, self.team_id, self.enterprise_id).execute(db).await
+    }
+
+    /// New todo in the workspace of `tenant`
+    pub fn new(tenant: &Tenant) -> Self {
+        Self {
+            team_id: tenant.team_id.clone(),
+            enterprise_id: tenant.enterprise_id.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub async fn fetch(id: uuid::Uuid, tenant: &Tenant, db: &PgPool) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<sqlx::Postgres, Todo>(&format!(
+            "{TODO_SELECT} WHERE t.id = $1 AND t.team_id = $2 AND t.enterprise_id = $3 LIMIT 1"
+        ))
+        .bind(id)
+        .bind(&tenant.team_id)
+        .bind(&tenant.enterprise_id)
+        .fetch_one(db)
+        .await
+    }
+
+    pub async fn set_completed(
+        &mut self,
+        completed: bool,
+        db: &PgPool,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        self.completed = completed;
+        self.completed_at = completed.then(Utc::now);
+
+        sqlx::query(r#"UPDATE todos SET completed = $1, completed_at = $2 WHERE id = $3 AND team_id = $4 AND enterprise_id = $5"#)
+            .bind(self.completed)
+            .bind(self.completed_at)
+            .bind(self.id)
+            .bind(&self.team_id)
+            .bind(&self.enterprise_id)
+            .execute(db)
+            .await
+    }
+
+    pub async fn update(&self, db: &PgPool) -> Result<PgQueryResult, sqlx::Error> {
+        sqlx::query(r#"UPDATE todos SET title = $1, description = $2 WHERE id = $3 AND team_id = $4 AND enterprise_id = $5"#)
+            .bind(self.title.clone())
+            .bind(self.description.clone())
+            .bind(self.id)
+            .bind(&self.team_id)
+            .bind(&self.enterprise_id)
+            .execute(db)
+            .await
+    }
+
+    pub async fn set_due(
+        &mut self,
+        due_at: Option<DateTime<Utc>>,
+        db: &PgPool,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        self.due_at = due_at;
+
+        sqlx::query(
+            r#"UPDATE todos SET due_at = $1 WHERE id = $2 AND team_id = $3 AND enterprise_id = $4"#,
+        )
+        .bind(self.due_at)
+        .bind(self.id)
+        .bind(&self.team_id)
+        .bind(&self.enterprise_id)
+        .execute(db)
+        .await
+    }
+
+    pub fn recurrence(&self) -> Option<Recurrence> {
+        self.recurrence.as_deref().and_then(|v| v.parse().ok())
+    }
+
+    /// Due date of the next occurrence, anchored on the due date so completing late doesn't
+    /// shift the schedule, or on the completion time `now` for todos without one. Occurrences
+    /// missed while the todo was overdue are skipped, so the next one is never overdue already
+    pub fn next_due(&self, now: DateTime<Utc>, tz: Tz) -> Option<DateTime<Utc>> {
+        let recurrence = self.recurrence()?;
+        let anchor = self.due_at.unwrap_or(now);
+        let time = anchor.with_timezone(&tz).time();
+
+        // Resolved from the anchor time every step, so a DST gap on the way doesn't move it
+        let mut next = recurrence.next(anchor, tz);
+        while next <= now {
+            let date = recurrence.next(next, tz).with_timezone(&tz).date_naive();
+            next = dates::resolve(date, time, tz);
+        }
+
+        Some(next)
+    }
+
+    /// Creates the next occurrence of a recurring todo, unless it was already created
+    pub async fn insert_next(&self, tz: Tz, db: &PgPool) -> Result<Option<Todo>, sqlx::Error> {
+        let due_at = match self.next_due(Utc::now(), tz) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        let exists: bool =
+            sqlx::query_scalar(r#"SELECT EXISTS (SELECT 1 FROM todos WHERE recurs_from = $1)"#)
+                .bind(self.id)
+                .fetch_one(db)
+                .await?;
+
+        if exists {
+            return Ok(None);
+        }
+
+        let mut next = Todo {
+            team_id: self.team_id.clone(),
+            enterprise_id: self.enterprise_id.clone(),
+            title: self.title.clone(),
+            description: self.description.clone(),
+            slack_user: self.slack_user.clone(),
+            created_by: self.created_by.clone(),
+            due_at: Some(due_at),
+            recurrence: self.recurrence.clone(),
+            recurs_from: Some(self.id),
+            priority: self.priority,
+            source_permalink: self.source_permalink.clone(),
+            tags: self.tags.clone(),
+            ..Default::default()
+        };
+        next.assign_id().insert(db).await?;
+        Tag::assign(next.id, &next.tags, db).await?;
+
+        Ok(Some(next))
+    }
+
+    /// Open todos of every workspace due after `from`, if any, until `until` that haven't had a `kind` reminder for their current due date
+    pub async fn fetch_unreminded(
+        kind: ReminderKind,
+        from: Option<DateTime<Utc>>,
+        until: DateTime<Utc>,
+        db: &PgPool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<sqlx::Postgres, Todo>(&format!(
+            r#"{TODO_SELECT} WHERE t.completed IS NOT TRUE AND t.deleted_at IS NULL AND ($1::timestamptz IS NULL OR t.due_at > $1) AND t.due_at <= $2
+            AND NOT EXISTS (SELECT 1 FROM reminders r WHERE r.todo_id = t.id AND r.kind = $3 AND r.due_at = t.due_at)"#
+        ))
+        .bind(from)
+        .bind(until)
+        .bind(kind)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Moves the todo to the trash, it can still be restored or purged
+    pub async fn delete(&mut self, db: &PgPool) -> Result<PgQueryResult, sqlx::Error> {
+        self.deleted_at = Some(Utc::now());
+
+        sqlx::query(r#"UPDATE todos SET deleted_at = $1 WHERE id = $2 AND team_id = $3 AND enterprise_id = $4"#)
+            .bind(self.deleted_at)
+            .bind(self.id)
+            .bind(&self.team_id)
+            .bind(&self.enterprise_id)
+            .execute(db)
+            .await
+    }
+
+    pub async fn restore(&mut self, db: &PgPool) -> Result<PgQueryResult, sqlx::Error> {
+        self.deleted_at = None;
+
+        sqlx::query(r#"UPDATE todos SET deleted_at = NULL WHERE id = $1 AND team_id = $2 AND enterprise_id = $3"#)
+            .bind(self.id)
+            .bind(&self.team_id)
+            .bind(&self.enterprise_id)
+            .execute(db)
+            .await
+    }
+
+    pub async fn purge(&self, db: &PgPool) -> Result<PgQueryResult, sqlx::Error> {
+        sqlx::query(r#"DELETE FROM todos WHERE id = $1 AND team_id = $2 AND enterprise_id = $3"#)
+            .bind(self.id)
+            .bind(&self.team_id)
+            .bind(&self.enterprise_id)
+            .execute(db)
+            .await
     }
 
     pub fn block(&mut self) -> SlackBlock {
         let mut template: HashMap<&str, String> = HashMap::new();
         template.insert("id", self.id.to_string());
-        template.insert("title", self.title.clone());
+        template.insert("title", json_string(&self.title));
+        template.insert(
+            "heading",
+            match &self.headline {
+                Some(headline) => json_string(headline),
+                None => format!("*{}*", json_string(&self.title)),
+            },
+        );
+        template.insert("priority", self.priority.badge().to_string());
         template.insert(
             "desc",
-            self.description
-                .clone()
-                .unwrap_or("_No Description_".to_string()),
+            json_string(self.description.as_deref().unwrap_or("_No Description_")),
+        );
+        template.insert("due", self.due());
+        template.insert(
+            "repeat",
+            self.recurrence()
+                .map(|v| format!("\\n:repeat: Repeats {}", v.describe()))
+                .unwrap_or_default(),
+        );
+        template.insert("tags", self.tags_line());
+        template.insert(
+            "assigned",
+            match &self.created_by {
+                Some(by) if *by != self.slack_user => {
+                    format!(
+                        "\\n:bust_in_silhouette: Assigned to <@{}> by <@{by}>",
+                        self.slack_user
+                    )
+                }
+                _ => String::new(),
+            },
+        );
+        template.insert(
+            "source",
+            self.source_permalink
+                .as_deref()
+                .map(|url| format!("\\n:link: <{}|View source>", json_string(url)))
+                .unwrap_or_default(),
         );
 
-        let mut block = SlackBlock::new("todo".to_string());
+        let mut block = SlackBlock::new(if self.deleted_at.is_some() {
+            "todo-deleted".to_string()
+        } else if self.completed {
+            "todo-completed".to_string()
+        } else {
+            "todo".to_string()
+        });
         block.load().fill(template);
 
         block
     }
+
+    /// Both the assignee and whoever created the todo can change it
+    pub fn can_update(&self, user: &str) -> bool {
+        self.slack_user == user || self.created_by.as_deref() == Some(user)
+    }
+
+    pub fn is_overdue(&self) -> bool {
+        !self.completed && self.due_at.is_some_and(|due| due < Utc::now())
+    }
+
+    /// Due date line for the todo blocks, empty when there is no due date
+    fn due(&self) -> String {
+        match self.due_at {
+            Some(due) if self.is_overdue() => {
+                format!("\\n:warning: *Overdue* since {}", slack_date(due))
+            }
+            Some(due) => format!("\\n:alarm_clock: Due {}", slack_date(due)),
+            None => String::new(),
+        }
+    }
+
+    /// Tags line for the todo blocks, empty when the todo has no tags
+    fn tags_line(&self) -> String {
+        if self.tags.is_empty() {
+            return String::new();
+        }
+
+        format!(
+            "\\n:label: {}",
+            self.tags
+                .iter()
+                .map(|tag| format!("`#{}`", json_string(tag)))
+                .collect::<Vec<String>>()
+                .join(" ")
+        )
+    }
+
+    /// One line overview of everything besides the title, for confirmations
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+
+        if self.priority != Priority::None {
+            parts.push(self.priority.badge().trim().to_string());
+        }
+        if !self.tags.is_empty() {
+            parts.push(
+                self.tags
+                    .iter()
+                    .map(|tag| format!("`#{tag}`"))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+            );
+        }
+        if let Some(due) = self.due_at {
+            parts.push(format!(":alarm_clock: Due {}", slack_date(due)));
+        }
+        if let Some(recurrence) = self.recurrence() {
+            parts.push(format!(":repeat: Repeats {}", recurrence.describe()));
+        }
+        if self.created_by.as_deref() != Some(self.slack_user.as_str()) {
+            parts.push(format!(
+                ":bust_in_silhouette: Assigned to <@{}>",
+                self.slack_user
+            ));
+        }
+
+        if parts.is_empty() {
+            return "_No priority, tags, due date or assignee_".to_string();
+        }
+
+        json_string(&parts.join(" · "))
+    }
+
+    pub fn blocks(&mut self) -> Vec<Value> {
+        serde_json::from_str(&format!("[{}]", self.block().data)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    async fn insert_due(due_at: DateTime<Utc>, db: &PgPool) -> Todo {
+        let mut todo = Todo {
+            title: "Ship it".to_string(),
+            slack_user: "U1".to_string(),
+            due_at: Some(due_at),
+            ..Default::default()
+        };
+        todo.assign_id().insert(db).await.unwrap();
+
+        todo
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn next_due_anchors_on_due_date() {
+        let todo = Todo {
+            due_at: Some(utc("2024-05-06T09:00:00Z")),
+            recurrence: Some("FREQ=DAILY".to_string()),
+            ..Default::default()
+        };
+
+        // Completed three days late, the missed days are skipped but not the time of day
+        assert_eq!(
+            todo.next_due(utc("2024-05-09T15:00:00Z"), Tz::UTC),
+            Some(utc("2024-05-10T09:00:00Z"))
+        );
+
+        // Completed early, the next one is still the day after the due date
+        assert_eq!(
+            todo.next_due(utc("2024-05-05T15:00:00Z"), Tz::UTC),
+            Some(utc("2024-05-07T09:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn next_due_skips_missed_occurrences_every_n_days() {
+        let todo = Todo {
+            due_at: Some(utc("2024-05-01T09:00:00Z")),
+            recurrence: Some("FREQ=DAILY;INTERVAL=3".to_string()),
+            ..Default::default()
+        };
+
+        // Keeps the cadence of the due date: 04, 07, 10
+        assert_eq!(
+            todo.next_due(utc("2024-05-09T15:00:00Z"), Tz::UTC),
+            Some(utc("2024-05-10T09:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn next_due_keeps_the_wall_clock_across_a_dst_gap() {
+        let tz: Tz = "Europe/Amsterdam".parse().unwrap();
+        // 02:30 CET, the same time doesn't exist on the 31st
+        let todo = Todo {
+            due_at: Some(utc("2024-03-30T01:30:00Z")),
+            recurrence: Some("FREQ=DAILY".to_string()),
+            ..Default::default()
+        };
+
+        // 02:30 CEST again once past the gap
+        assert_eq!(
+            todo.next_due(utc("2024-04-02T12:00:00Z"), tz),
+            Some(utc("2024-04-03T00:30:00Z"))
+        );
+    }
+
+    #[test]
+    fn next_due_anchors_on_completion_without_due_date() {
+        let todo = Todo {
+            recurrence: Some("FREQ=WEEKLY;BYDAY=MO".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            todo.next_due(utc("2024-05-09T15:00:00Z"), Tz::UTC),
+            Some(utc("2024-05-13T15:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn next_due_without_recurrence() {
+        let todo = Todo {
+            due_at: Some(utc("2024-05-06T09:00:00Z")),
+            ..Default::default()
+        };
+
+        assert_eq!(todo.next_due(Utc::now(), Tz::UTC), None);
+    }
+
+    #[test]
+    fn search_headline_replaces_the_bold_title() {
+        let mut todo = Todo {
+            title: "Fix the bug".to_string(),
+            ..Default::default()
+        };
+        assert!(todo.block().data.contains(r#""text": "*Fix the bug*"#));
+
+        todo.headline = Some("Fix the *bug*".to_string());
+        let data = todo.block().data;
+        assert!(data.contains(r#""text": "Fix the *bug*"#));
+        assert!(!data.contains("*Fix the *bug**"));
+    }
+
+    #[sqlx::test]
+    async fn insert_next_once_per_occurrence(db: PgPool) {
+        // Due in the future, so the next occurrence isn't pushed past today
+        let mut todo = insert_due(utc("2099-01-31T09:00:00Z"), &db).await;
+        todo.recurrence = Some("FREQ=MONTHLY;BYMONTHDAY=31".to_string());
+
+        let next = todo.insert_next(Tz::UTC, &db).await.unwrap().unwrap();
+        assert_eq!(next.recurs_from, Some(todo.id));
+        assert_eq!(next.due_at, Some(utc("2099-02-28T09:00:00Z")));
+
+        assert!(todo.insert_next(Tz::UTC, &db).await.unwrap().is_none());
+    }
+
+    #[sqlx::test]
+    async fn fetch_unreminded_overdue_without_lower_bound(db: PgPool) {
+        let now = Utc::now();
+        let overdue = insert_due(now - Duration::days(400), &db).await;
+        insert_due(now + Duration::hours(1), &db).await;
+
+        let todos = Todo::fetch_unreminded(ReminderKind::Overdue, None, now, &db)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            todos.iter().map(|v| v.id).collect::<Vec<_>>(),
+            vec![overdue.id]
+        );
+    }
+
+    #[sqlx::test]
+    async fn fetch_unreminded_upcoming_within_lead(db: PgPool) {
+        let now = Utc::now();
+        insert_due(now - Duration::hours(1), &db).await;
+        let upcoming = insert_due(now + Duration::minutes(10), &db).await;
+
+        let todos = Todo::fetch_unreminded(
+            ReminderKind::Upcoming,
+            Some(now),
+            now + Duration::minutes(30),
+            &db,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            todos.iter().map(|v| v.id).collect::<Vec<_>>(),
+            vec![upcoming.id]
+        );
+    }
 }